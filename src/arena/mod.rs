@@ -176,4 +176,25 @@ impl<T> Arena<T> {
         let others = others.map( |i| i.and_then( |i| self.get(i) ) );
         (x.value_mut(), others)
     }
+    #[inline]
+    fn get_many_mut<const N: usize>(&mut self, indices: [Option<Index>; N]) -> Result<[Option<&mut T>; N], Error> {
+        for (n, index) in indices.iter().enumerate() {
+            if index.is_some() && indices[..n].contains(index) {
+                return Err(Error::IndexAlias);
+            }
+        }
+        // SAFETY: indices are checked explicitly before
+        Ok(unsafe { self.get_many_mut_unchecked(indices) })
+    }
+    /// # Safety
+    /// No alias checking is done.
+    #[inline]
+    unsafe fn get_many_mut_unchecked<const N: usize>(&mut self, indices: [Option<Index>; N]) -> [Option<&mut T>; N] {
+        let len = self.items.len();
+        let ptr = self.items.as_mut_ptr();
+        indices.map( |i| i
+            .filter( |i| i.0 < len )
+            .and_then( |i| ptr.add(i.0).as_mut().unwrap().value_mut() )
+        )
+    }
 }
@@ -31,6 +31,10 @@ impl<T, M> Port<T, M> {
         Self(self.0.clone(), RwLock::new(meta))
     }
     #[inline]
+    pub(crate) fn shares_arena(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+    #[inline]
     pub fn read(&self) -> PortReadGuard<T, M> {
         let arena = self.0.read();
         let port = self.1.read();
@@ -101,6 +105,10 @@ impl<'a, T, M> Writer<Index, Error> for PortWriteGuard<'a, T, M> {
     fn get_mut_with<const N: usize>(&mut self, index: Index, others: [Option<Index>; N]) -> Result<(Option<&mut T>, [Option<&T>; N]), Error> {
         self.arena_mut().get_mut_with(index, others)
     }
+    #[inline]
+    fn get_many_mut<const N: usize>(&mut self, indices: [Option<Index>; N]) -> Result<[Option<&mut T>; N], Error> {
+        self.arena_mut().get_many_mut(indices)
+    }
 }
 
 #[derive(Debug)]
@@ -153,6 +161,10 @@ impl<'a, T, M> Writer<Index, Error> for PortAllocGuard<'a, T, M> {
     fn get_mut_with<const N: usize>(&mut self, index: Index, others: [Option<Index>; N]) -> Result<(Option<&mut T>, [Option<&T>; N]), Error> {
         self.arena_mut().get_mut_with(index, others)
     }
+    #[inline]
+    fn get_many_mut<const N: usize>(&mut self, indices: [Option<Index>; N]) -> Result<[Option<&mut T>; N], Error> {
+        self.arena_mut().get_many_mut(indices)
+    }
 }
 
 macro_rules! impl_Meta {
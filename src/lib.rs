@@ -13,14 +13,17 @@
 mod arena;
 pub mod tree;
 
-use std::ops::{ControlFlow, FromResidual, Try};
+use std::{
+    ops::{ControlFlow, FromResidual, Try},
+    sync::Arc
+};
 
 #[cfg(feature = "sorted-iter")]
 use sorted_iter::sorted_pair_iterator::SortedByKey;
 
 use crate::{
     arena::{Arena, Port},
-    tree::{Tree, Node, Bounds, Value, NoCumulant}
+    tree::{Tree, MultiTree, Node, Bounds, Value, NoCumulant, OrdComparator}
 };
 
 struct DeferDiscard(bool);
@@ -75,37 +78,69 @@ trait Writer<T, E>: Reader<T> {
     fn get_pair_mut(&mut self, a: T, b: T) -> Result<[Option<&mut Self::Item>; 2], E>;
     #[allow(clippy::type_complexity)]
     fn get_mut_with<const N: usize>(&mut self, idnex: T, others: [Option<T>; N]) -> Result<(Option<&mut Self::Item>, [Option<&Self::Item>; N]), E>;
+    fn get_many_mut<const N: usize>(&mut self, indices: [Option<T>; N]) -> Result<[Option<&mut Self::Item>; N], E>;
 }
 /// Collection of [Tree] that transfers ownership of its elements to the caller.
+///
+/// All trees of a `WeakForest` are ordered by the same [Comparator](crate::tree::Comparator).
 #[derive(Debug)]
-pub struct WeakForest<K: Ord, V: Value> {
+pub struct WeakForest<K, V: Value, C = OrdComparator> {
     free_port: Port<Node<K, V>, Bounds>,
+    cmp: Arc<C>
 }
-impl<K: Ord, V: Value> WeakForest<K, V> {
+impl<K, V: Value> WeakForest<K, V> {
     /// Construct an empty `WeakForest`.
     ///
     /// There will be no allocations until the first node is inserted into any tree.
     #[inline]
     pub fn new() -> Self {
-        Self { free_port: Arena::new().into_port(Bounds::default()) }
+        Self::with_comparator(OrdComparator)
     }
     /// Construct an empty `WeakForest` and preallocate enough memory to hold `capacity` nodes.
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
-        Self { free_port: Arena::with_capacity(capacity).into_port(Bounds::default()) }
+        Self::with_capacity_and_comparator(capacity, OrdComparator)
+    }
+    /// Construct and insert a new tree into the `WeakForest` with elements taken from a sorted iterator.
+    #[cfg(feature = "sorted-iter")]
+    #[inline]
+    pub fn insert_sorted_iter(&mut self, iter: impl IntoIterator<Item = (K, V::Local)> + SortedByKey) -> Tree<K, V> {
+        // SAFETY: guarantied by trait
+        unsafe { self.insert_sorted_iter_unchecked(iter) }
+    }
+}
+impl<K, V: Value, C> WeakForest<K, V, C> {
+    /// Construct an empty `WeakForest` using the given comparator.
+    ///
+    /// There will be no allocations until the first node is inserted into any tree.
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        Self { free_port: Arena::new().into_port(Bounds::default()), cmp: Arc::new(cmp) }
+    }
+    /// Construct an empty `WeakForest` using the given comparator and preallocate enough memory to hold `capacity` nodes.
+    #[inline]
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
+        Self { free_port: Arena::with_capacity(capacity).into_port(Bounds::default()), cmp: Arc::new(cmp) }
+    }
+    /// Returns the comparator shared by all trees of this `WeakForest`.
+    #[inline(always)]
+    pub fn comparator(&self) -> &C {
+        &self.cmp
     }
     /// Insert a new tree into the `WeakForest`, ownership will be transfered to the caller.
     #[inline]
-    pub fn insert(&mut self) -> Tree<K, V> {
-        Tree::new(self.free_port.split_with_meta(Bounds::default()))
+    pub fn insert(&mut self) -> Tree<K, V, C> {
+        Tree::new(self.free_port.split_with_meta(Bounds::default()), self.cmp.clone())
     }
     /// Construct and insert a new tree into the `WeakForest` with elements taken from a sorted slice.
     ///
     /// # Safety
     /// It is assumed that the given slice is sorted by K in incresing order.
     #[inline]
-    pub unsafe fn insert_sorted_slice_unchecked(&mut self, items: &[(K, V::Local)]) -> Tree<K, V> {
-        Tree::from_sorted_slice_unchecked(self.free_port.split_with_meta(Bounds::default()), items)
+    pub unsafe fn insert_sorted_slice_unchecked(&mut self, items: &[(K, V::Local)]) -> Tree<K, V, C>
+        where K: Clone, V::Local: Clone
+    {
+        Tree::from_sorted_vec_unchecked(self.insert(), items.to_vec())
     }
     /// Construct and insert a new tree into the `WeakForest` with elements taken from a sorted iterator.
     ///
@@ -114,29 +149,72 @@ impl<K: Ord, V: Value> WeakForest<K, V> {
     ///
     /// For a safe version of this function use the 'sorted-iter' feature.
     #[inline]
-    pub unsafe fn insert_sorted_iter_unchecked(&mut self, iter: impl IntoIterator<Item = (K, V::Local)>) -> Tree<K, V> {
-        Tree::from_sorted_iter_unchecked(self.free_port.split_with_meta(Bounds::default()), iter)
-    }
-    /// Construct and insert a new tree into the `WeakForest` with elements taken from a sorted iterator.
-    #[cfg(feature = "sorted-iter")]
-    #[inline]
-    pub fn insert_sorted_iter(&mut self, iter: impl IntoIterator<Item = (K, V::Local)> + SortedByKey) -> Tree<K, V> {
-        Tree::from_sorted_iter(self.free_port.split_with_meta(Bounds::default()), iter)
+    pub unsafe fn insert_sorted_iter_unchecked(&mut self, iter: impl IntoIterator<Item = (K, V::Local)>) -> Tree<K, V, C> {
+        Tree::from_sorted_iter_unchecked(self.insert(), iter)
     }
 }
-impl<K: Ord, V: Value> Default for WeakForest<K, V> {
+impl<K, V: Value, C: Default> Default for WeakForest<K, V, C> {
     #[inline(always)]
-    fn default() -> Self { Self::new() }
+    fn default() -> Self { Self::with_comparator(C::default()) }
 }
 /// Type alias for a [WeakForest] without cumulants.
 pub type SimpleWeakForest<K, V> = WeakForest<K, NoCumulant<V>>;
+/// Collection of [MultiTree] that transfers ownership of its elements to the caller.
+///
+/// Trees of a `MultiWeakForest` allow duplicate keys.
+#[derive(Debug)]
+pub struct MultiWeakForest<K, V: Value, C = OrdComparator>(WeakForest<K, V, C>);
+impl<K, V: Value> MultiWeakForest<K, V> {
+    /// Construct an empty `MultiWeakForest`.
+    ///
+    /// There will be no allocations until the first node is inserted into any tree.
+    #[inline]
+    pub fn new() -> Self {
+        Self(WeakForest::new())
+    }
+    /// Construct an empty `MultiWeakForest` and preallocate enough memory to hold `capacity` nodes.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(WeakForest::with_capacity(capacity))
+    }
+}
+impl<K, V: Value, C> MultiWeakForest<K, V, C> {
+    /// Construct an empty `MultiWeakForest` using the given comparator.
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        Self(WeakForest::with_comparator(cmp))
+    }
+    /// Construct an empty `MultiWeakForest` using the given comparator and preallocate enough memory to hold `capacity` nodes.
+    #[inline]
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
+        Self(WeakForest::with_capacity_and_comparator(capacity, cmp))
+    }
+    /// Returns the comparator shared by all trees of this `MultiWeakForest`.
+    #[inline(always)]
+    pub fn comparator(&self) -> &C {
+        self.0.comparator()
+    }
+    /// Insert a new tree into the `MultiWeakForest`, ownership will be transfered to the caller.
+    #[inline]
+    pub fn insert(&mut self) -> MultiTree<K, V, C> {
+        MultiTree(self.0.insert())
+    }
+}
+impl<K, V: Value, C: Default> Default for MultiWeakForest<K, V, C> {
+    #[inline(always)]
+    fn default() -> Self { Self(WeakForest::default()) }
+}
+/// Type alias for a [MultiWeakForest] without cumulants.
+pub type SimpleMultiWeakForest<K, V> = MultiWeakForest<K, NoCumulant<V>>;
 
 pub mod prelude {
     pub use crate::{
         WeakForest, SimpleWeakForest,
+        MultiWeakForest, SimpleMultiWeakForest,
         tree::{
             NoCumulant, with_cumulant,
-            SearchResult, SearchAction
+            Comparator, OrdComparator,
+            SearchResult, SearchAction, MergeAction
         }
     };
 }
@@ -5,6 +5,14 @@ with_cumulant!(
     WithSum<T: Sum>(v: &T, c: [&T] = T::default()) {
         *v + *c[0] + *c[1]
     }
+    combine(a, b) {
+        *a + *b
+    }
+);
+with_cumulant!(
+    WithMax(v: &usize, c: [&usize] = 0) {
+        *v.max(c[0]).max(c[1])
+    }
 );
 
 #[test]
@@ -25,7 +33,7 @@ fn insert_remove() {
         }
         for x in values.into_iter() {
             println!("==================== -{} ====================", x);
-            let value = alloc.remove(x);
+            let value = alloc.remove(&x);
             print_tree(&alloc.0);
             validate_rb_tree(&alloc.0);
             assert_eq!(value, Some(x));
@@ -40,6 +48,56 @@ fn insert_remove() {
     }
 }
 #[test]
+fn entry() {
+    const N: usize = 10;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for x in 0..N {
+            alloc.entry(x % 3).and_modify( |(v, _)| **v += x ).or_insert(x);
+            validate_rb_tree(&alloc.0);
+        }
+        assert_eq!(alloc.cumulant().copied(), Some((N * (N - 1)) >> 1));
+        match alloc.entry(1) {
+            Entry::Occupied(mut entry) => assert_eq!(entry.insert(0), 1 + 4 + 7),
+            Entry::Vacant(_) => panic!("key should exist")
+        }
+        assert_eq!(alloc.cumulant().copied(), Some(((N * (N - 1)) >> 1) - 12));
+        *alloc.entry(1).or_insert(0).0.0 = 12;
+        assert_eq!(alloc.cumulant().copied(), Some((N * (N - 1)) >> 1));
+        *alloc.get_mut(&0).unwrap().0.0 = 0;
+        assert_eq!(alloc.cumulant().copied(), Some(((N * (N - 1)) >> 1) - 18));
+    }
+}
+#[test]
+fn retain() {
+    const N: usize = 50;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    for m in [5, 2] {
+        let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+            (0..N).map( |n| (n, n) )
+        ) };
+        {
+            let mut alloc = tree.alloc();
+            alloc.retain( |k, (v, _)| {
+                *v *= 2;
+                k % m == 0
+            } );
+            validate_rb_tree(&alloc.0);
+            let sum = (0..N).filter( |k| k % m == 0 ).map( |k| k * 2 ).sum::<usize>();
+            assert_eq!(alloc.cumulant().copied(), Some(sum));
+            let count = alloc.len();
+            let extracted = alloc.extract_if( |k, (v, _)| {
+                *v += 1;
+                k % 4 == 0
+            } ).map( |(_, v)| v ).sum::<usize>();
+            validate_rb_tree(&alloc.0);
+            assert_eq!(alloc.cumulant().copied(), Some(sum + count - extracted));
+        }
+    }
+}
+#[test]
 fn union() {
     const N: usize = 10;
     let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N << 1);
@@ -61,7 +119,7 @@ fn union() {
         validate_rb_tree(&read.0);
         assert_eq!(read.cumulant().copied(), Some(N * N));
     }
-    let all = odd.union_merge(even, |_, _| panic!("duplicate key") );
+    let all = odd.union_merge(even, |_, _| panic!("duplicate key") ).unwrap();
     {
         let read = all.read();
         print_tree(&read.0);
@@ -88,4 +146,300 @@ fn iter_mut() {
         validate_rb_tree(&write.0);
         assert_eq!(write.cumulant().copied(), Some(N * (N - 1)));
     }
-}
\ No newline at end of file
+}
+#[test]
+fn pop() {
+    const N: usize = 10;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, n) )
+    ) };
+    {
+        let mut alloc = tree.alloc();
+        assert_eq!(alloc.first_key_value(), Some((&0, (&0, &0))));
+        assert_eq!(alloc.last_key_value().map( |(k, (v, _))| (*k, *v) ), Some((N - 1, N - 1)));
+        *alloc.first_mut().unwrap().0.0 = 100;
+        *alloc.last_mut().unwrap().0.0 = 200;
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.cumulant().copied(), Some(((N * (N - 1)) >> 1) + 300 - (N - 1)));
+        assert_eq!(alloc.pop_first(), Some((0, 100)));
+        assert_eq!(alloc.pop_last(), Some((N - 1, 200)));
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.cumulant().copied(), Some(((N * (N - 1)) >> 1) - (N - 1)));
+        let mut keys = Vec::new();
+        while let Some((key, _)) = alloc.pop_first() {
+            validate_rb_tree(&alloc.0);
+            keys.push(key);
+        }
+        assert_eq!(keys, (1..(N - 1)).collect::<Vec<_>>());
+        assert_eq!(alloc.pop_last(), None);
+        assert!(alloc.first_mut().is_none());
+    }
+}
+#[test]
+fn range_mut() {
+    const N: usize = 10;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, n) )
+    ) };
+    {
+        let mut write = tree.write();
+        for (_k, (v, _c)) in write.range_mut(3..7) {
+            *v = 0;
+        }
+        validate_rb_tree(&write.0);
+        assert_eq!(write.cumulant().copied(), Some(((N * (N - 1)) >> 1) - 18));
+        assert_eq!(write.range_mut(20..).count(), 0);
+    }
+}
+#[test]
+fn rekey() {
+    const N: usize = 10;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n << 1, n) )
+    ) };
+    {
+        let mut alloc = tree.alloc();
+        let sum = (N * (N - 1)) >> 1;
+        alloc.rekey(&4, 5).unwrap();
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.get(&4), None);
+        assert_eq!(alloc.get(&5).map( |(v, _)| *v ), Some(2));
+        alloc.rekey(&8, 100).unwrap();
+        alloc.rekey(&0, 7).unwrap();
+        alloc.rekey(&100, 1).unwrap();
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.len(), N);
+        assert_eq!(alloc.cumulant().copied(), Some(sum));
+        assert_eq!(alloc.iter().map( |(k, (v, _))| (*k, *v) ).collect::<Vec<_>>(),
+            vec![(1, 4), (2, 1), (5, 2), (6, 3), (7, 0), (10, 5), (12, 6), (14, 7), (16, 8), (18, 9)]);
+        assert!(matches!(alloc.rekey(&2, 6), Err((6, Error::DuplicateKey))));
+        assert!(matches!(alloc.rekey(&3, 4), Err((4, Error::MissingKey))));
+        validate_rb_tree(&alloc.0);
+    }
+}
+#[test]
+fn get_many_mut() {
+    const N: usize = 10;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, n) )
+    ) };
+    {
+        let mut write = tree.write();
+        {
+            let mut many = write.get_many_mut([&1, &5, &N, &8]).unwrap();
+            let [a, b, c, d] = many.get();
+            assert!(c.is_none());
+            *a.unwrap().0 += 10;
+            *b.unwrap().0 += 20;
+            *d.unwrap().0 += 30;
+        }
+        validate_rb_tree(&write.0);
+        assert_eq!(write.cumulant().copied(), Some(((N * (N - 1)) >> 1) + 60));
+        assert_eq!(write.get(&5).map( |(v, _)| *v ), Some(25));
+        assert!(matches!(write.get_many_mut([&1, &2, &1]), Err(Error::KeyAlias)));
+        let root = write.0.meta().root.unwrap();
+        let [left, right] = write.0[root].children.map( |child| write.0[child.unwrap()].key );
+        let root = write.0[root].key;
+        {
+            // NOTE: the root is an ancestor of both other keys
+            let mut many = write.get_many_mut([&left, &root, &right]).unwrap();
+            for value in many.get() {
+                *value.unwrap().0 += 100;
+            }
+        }
+        validate_rb_tree(&write.0);
+        assert_eq!(write.cumulant().copied(), Some(((N * (N - 1)) >> 1) + 360));
+    }
+}
+#[test]
+fn union_with() {
+    const N: usize = 20;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N << 1);
+    let tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).step_by(2).map( |n| (n, n) )
+    ) };
+    let other = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).step_by(3).map( |n| (n, 1) )
+    ) };
+    let mut merged = Vec::new();
+    let tree = tree.union_with(other, |k, v, other| {
+        merged.push(*k);
+        if k % 4 == 0 {
+            return MergeAction::Remove;
+        }
+        *v.0 += other.0;
+        MergeAction::Keep
+    }).unwrap();
+    merged.sort_unstable();
+    assert_eq!(merged, vec![0, 6, 12, 18]);
+    {
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        let items = read.iter().map( |(k, (v, _))| (*k, *v) ).collect::<Vec<_>>();
+        let expected = (0..N)
+            .filter( |k| (k % 2 == 0 || k % 3 == 0) && k % 12 != 0 )
+            .map( |k| (k, if k % 2 == 0 { k + (k % 3 == 0) as usize } else { 1 }) )
+            .collect::<Vec<_>>();
+        assert_eq!(items, expected);
+        assert_eq!(read.cumulant().copied(), Some(expected.iter().map( |(_, v)| v ).sum()));
+    }
+}
+#[test]
+fn split_by_cumulant() {
+    const N: usize = 30;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    for limit in [0, 1, 2, 10, 100, 300, 434, 435, 436, 1000] {
+        let tree = unsafe { forest.insert_sorted_iter_unchecked(
+            (0..N).map( |n| (n, n) )
+        ) };
+        let (left, right) = tree.split_by_cumulant( |sum| *sum > limit );
+        let (left, right) = (left.read(), right.read());
+        validate_rb_tree(&left.0);
+        validate_rb_tree(&right.0);
+        let expected = (0..=N).find( |&n| (n * (n + 1)) >> 1 > limit ).unwrap_or(N);
+        assert_eq!(left.len(), expected, "limit {limit}");
+        assert_eq!(left.len() + right.len(), N);
+        assert!(left.cumulant().map_or(0, |sum| *sum) <= limit);
+        assert_eq!(right.min().copied(), (expected < N).then_some(expected));
+    }
+}
+#[test]
+fn without_combine() {
+    const N: usize = 30;
+    let mut forest: WeakForest<_, WithMax> = WeakForest::with_capacity(N);
+    let tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, (n * 7) % N) )
+    ) };
+    let (left, right) = tree.split_at(N >> 1);
+    assert_eq!(left.read().cumulant().copied(), (0..N >> 1).map( |n| (n * 7) % N ).max());
+    assert_eq!(right.read().cumulant().copied(), (N >> 1..N).map( |n| (n * 7) % N ).max());
+}
+#[test]
+fn join_with() {
+    const N: usize = 20;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N << 1);
+    for (a, b) in [(0, 0), (0, 5), (5, 0), (1, 15), (15, 1), (9, 10)] {
+        let left = unsafe { forest.insert_sorted_iter_unchecked((0..a).map( |n| (n, n) )) };
+        let right = unsafe { forest.insert_sorted_iter_unchecked((0..b).map( |n| (n + N, n + N) )) };
+        let tree = left.join_with(a, a, right).unwrap();
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        assert_eq!(read.len(), a + b + 1);
+        let sum = (0..=a).chain(N..N + b).sum::<usize>();
+        assert_eq!(read.cumulant().copied(), Some(sum));
+    }
+    let left = unsafe { forest.insert_sorted_iter_unchecked((0..5).map( |n| (n, n) )) };
+    let right = unsafe { forest.insert_sorted_iter_unchecked((5..10).map( |n| (n, n) )) };
+    let ((left, (key, _), right), err) = left.join_with(5, 0, right).unwrap_err();
+    assert!(matches!(err, Error::Overlapping));
+    assert_eq!(key, 5);
+    let mut other: WeakForest<_, WithSum<_>> = WeakForest::new();
+    let foreign = other.insert();
+    let ((left, _, _), err) = left.join_with(10, 0, foreign).unwrap_err();
+    assert!(matches!(err, Error::ForeignTree));
+    let ((left, _, _), err) = left.join_with(4, 0, right).unwrap_err();
+    assert!(matches!(err, Error::Overlapping));
+    let right = unsafe { forest.insert_sorted_iter_unchecked((6..10).map( |n| (n, n) )) };
+    let tree = left.join_with(5, 5, right).unwrap();
+    assert_eq!(tree.read().iter().map( |(k, _)| *k ).collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+}
+#[test]
+fn partition() {
+    const N: usize = 40;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    for m in [1, 2, 3, 7, N + 1] {
+        let tree = unsafe { forest.insert_sorted_iter_unchecked(
+            (0..N).map( |n| (n, n) )
+        ) };
+        let (matched, rest) = tree.partition( |k, (v, _)| k % m == 0 && *v < N );
+        for (tree, is_match) in [(matched.read(), true), (rest.read(), false)] {
+            validate_rb_tree(&tree.0);
+            let expected = (0..N).filter( |n| (n % m == 0) == is_match ).collect::<Vec<_>>();
+            assert_eq!(tree.iter().map( |(k, _)| *k ).collect::<Vec<_>>(), expected);
+            assert_eq!(tree.cumulant().copied(), (!expected.is_empty()).then(|| expected.iter().sum()));
+        }
+    }
+}
+#[test]
+fn value_mut_drop() {
+    const N: usize = 10;
+    fn is_sync<T: Sync>(_: &T) {}
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, n) )
+    ) };
+    let sum = (N * (N - 1)) >> 1;
+    {
+        // NOTE: dropping a ValueMut while the guard is alive used to lock the tree a second time
+        let mut write = tree.write();
+        let value = write.get_mut(&3).unwrap();
+        is_sync(&value);
+        *value.0.0 += 10;
+        drop(value);
+        assert_eq!(write.cumulant().copied(), Some(sum + 10));
+        *write.first_mut().unwrap().0.0 += 1;
+        *write.last_mut().unwrap().0.0 += 1;
+        validate_rb_tree(&write.0);
+        assert_eq!(write.cumulant().copied(), Some(sum + 12));
+    }
+    assert_eq!(tree.read().cumulant().copied(), Some(sum + 12));
+}
+#[test]
+fn get_pair_mut() {
+    const N: usize = 15;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, n) )
+    ) };
+    let sum = (N * (N - 1)) >> 1;
+    let mut write = tree.write();
+    let root = write.0.meta().root.unwrap();
+    let child = write.0[root].children[0].unwrap();
+    let (root, child) = (write.0[root].key, write.0[child].key);
+    {
+        // NOTE: the root is an ancestor of child, both are propagated after the references are gone
+        let mut pair = write.get_pair_mut(&root, &child).unwrap();
+        let [a, b] = pair.get();
+        *a.unwrap().0 += 10;
+        *b.unwrap().0 += 20;
+    }
+    validate_rb_tree(&write.0);
+    assert_eq!(write.cumulant().copied(), Some(sum + 30));
+    {
+        let mut with = write.get_mut_with(&child, [Some(&root), Some(&N)]).unwrap();
+        let ([x], [a, b]) = with.get_with();
+        *x.unwrap().0 += *a.unwrap().0;
+        assert!(b.is_none());
+    }
+    validate_rb_tree(&write.0);
+    assert_eq!(write.cumulant().copied(), Some(sum + 40 + root));
+    assert!(matches!(write.get_pair_mut(&root, &root), Err(Error::KeyAlias)));
+    assert!(matches!(write.get_mut_with(&root, [Some(&root)]), Err(Error::KeyAlias)));
+}
+#[test]
+fn remove_range() {
+    use std::ops::{Bound::*, RangeBounds};
+    const N: usize = 60;
+    let mut forest: WeakForest<_, WithSum<_>> = WeakForest::with_capacity(N);
+    let ranges = [
+        (Included(10), Excluded(20)), (Excluded(10), Included(20)), (Unbounded, Included(0)),
+        (Excluded(N - 1), Unbounded), (Included(30), Included(30)), (Excluded(5), Excluded(6)),
+        (Unbounded, Unbounded), (Included(N), Unbounded)
+    ];
+    for range in ranges {
+        let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+            (0..N).map( |n| (n, n) )
+        ) };
+        let mut alloc = tree.alloc();
+        let expected = (0..N).filter( |n| !range.contains(n) ).collect::<Vec<_>>();
+        assert_eq!(alloc.remove_range(range), N - expected.len());
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.iter().map( |(k, _)| *k ).collect::<Vec<_>>(), expected);
+        assert_eq!(alloc.cumulant().copied(), (!expected.is_empty()).then(|| expected.iter().sum()));
+        alloc.clear();
+    }
+}
@@ -7,16 +7,24 @@ use sorted_iter::assume::AssumeSortedByKeyExt;
 
 use crate::{
     prelude::*,
-    tree::{NodeIndex, NodeRef, Value, TreeReader}
+    tree::{Tree, NodeIndex, NodeRef, Value, CombineCumulant, TreeReader, Entry, Error}
 };
 
-fn validate_rb_node<'a, K, V>(index: NodeIndex,
-    tree: &'a impl TreeReader<K, V>
+fn ordered<const UNIQUE: bool, K, C: Comparator<K>>(a: &K, b: &K, cmp: &C) -> bool {
+    let order = cmp.compare(a, b);
+    if UNIQUE { order.is_lt() } else { order.is_le() }
+}
+fn validate_rb_node<'a, const UNIQUE: bool, K, V, C>(index: NodeIndex,
+    tree: &'a impl TreeReader<K, V>, cmp: &C
 ) -> ([&'a K; 2], u8)
-    where K: Ord + std::fmt::Debug, V: Value + 'a
+    where K: std::fmt::Debug, V: Value + 'a, C: Comparator<K>
 {
     let node = &tree[index];
     assert!(node.parent.is_some() || node.is_black(), "root has too be black");
+    let size = 1 + node.children.iter()
+        .map( |child| child.map_or(0, |child| tree[child].size) )
+        .sum::<usize>();
+    assert_eq!(node.size, size, "size has to match the sizes of the children");
     match node.order {
         [None, None] => {
             assert_eq!(node.parent, None, "order implies root");
@@ -24,18 +32,18 @@ fn validate_rb_node<'a, K, V>(index: NodeIndex,
         [Some(prev), None] => {
             let prev_node = &tree[prev];
             assert_eq!(node.children[1], None, "order implies max node");
-            assert!(prev_node.key < node.key, "out of bounds");
+            assert!(ordered::<UNIQUE, _, _>(&prev_node.key, &node.key, cmp), "out of bounds");
         },
         [None, Some(next)] => {
             let next_node = &tree[next];
             assert_eq!(node.children[0], None, "order implies min node");
-            assert!(node.key < next_node.key, "out of bounds");
+            assert!(ordered::<UNIQUE, _, _>(&node.key, &next_node.key, cmp), "out of bounds");
         },
         [Some(prev), Some(next)] => {
             let prev_node = &tree[prev];
             let next_node = &tree[next];
-            assert!(prev_node.key < node.key, "out of bounds");
-            assert!(node.key < next_node.key, "out of bounds");
+            assert!(ordered::<UNIQUE, _, _>(&prev_node.key, &node.key, cmp), "out of bounds");
+            assert!(ordered::<UNIQUE, _, _>(&node.key, &next_node.key, cmp), "out of bounds");
         }
     }
     match node.children {
@@ -44,36 +52,36 @@ fn validate_rb_node<'a, K, V>(index: NodeIndex,
             let left_node = &tree[left];
             assert!(node.is_black() || left_node.is_black(), "cannot have two red nodes in a row");
             assert!(left_node.is_red(), "single child has to be red");
-            let ([min, prev], left_height) = validate_rb_node(left, tree);
-            assert!(min <= prev, "bad order");
-            assert!(*prev < node.key, "left tree overlap");
-            assert_eq!(*prev, tree[node.order[0].expect("not null")].key, "biggest node of left sub-tree has to be prev");
+            let ([min, prev], left_height) = validate_rb_node::<UNIQUE, _, _, _>(left, tree, cmp);
+            assert!(cmp.compare(min, prev).is_le(), "bad order");
+            assert!(ordered::<UNIQUE, _, _>(prev, &node.key, cmp), "left tree overlap");
+            assert!(std::ptr::eq(prev, &tree[node.order[0].expect("not null")].key), "biggest node of left sub-tree has to be prev");
             ([min, &node.key], left_height + (node.color as u8))
         },
         [None, Some(right)] => {
             let right_node = &tree[right];
             assert!(node.is_black() || right_node.is_black(), "cannot have two red nodes in a row");
             assert!(right_node.is_red(), "single child has to be red");
-            let ([next, max], right_height) = validate_rb_node(right, tree);
-            assert!(next <= max, "bad order");
-            assert!(node.key < *next, "right tree overlap");
-            assert_eq!(*next, tree[node.order[1].expect("not null")].key, "smallest node of right sub-tree has to be next");
+            let ([next, max], right_height) = validate_rb_node::<UNIQUE, _, _, _>(right, tree, cmp);
+            assert!(cmp.compare(next, max).is_le(), "bad order");
+            assert!(ordered::<UNIQUE, _, _>(&node.key, next, cmp), "right tree overlap");
+            assert!(std::ptr::eq(next, &tree[node.order[1].expect("not null")].key), "smallest node of right sub-tree has to be next");
             ([&node.key, max], right_height + (node.color as u8))
         }
         [Some(left), Some(right)] => {
             let left_node = &tree[left];
             assert!(node.is_black() || left_node.is_black(), "cannot have two red nodes in a row");
-            let ([min, prev], left_height) = validate_rb_node(left, tree);
-            assert!(min <= prev, "bad order");
-            assert!(*prev < node.key, "left tree overlap");
-            assert_eq!(*prev, tree[node.order[0].expect("not null")].key, "biggest node of left sub-tree has to be prev");
+            let ([min, prev], left_height) = validate_rb_node::<UNIQUE, _, _, _>(left, tree, cmp);
+            assert!(cmp.compare(min, prev).is_le(), "bad order");
+            assert!(ordered::<UNIQUE, _, _>(prev, &node.key, cmp), "left tree overlap");
+            assert!(std::ptr::eq(prev, &tree[node.order[0].expect("not null")].key), "biggest node of left sub-tree has to be prev");
 
             let right_node = &tree[right];
             assert!(node.is_black() || right_node.is_black(), "cannot have two red nodes in a row");
-            let ([next, max], right_height) = validate_rb_node(right, tree);
-            assert!(next <= max, "bad order");
-            assert!(node.key < *next, "right tree overlap");
-            assert_eq!(*next, tree[node.order[1].expect("not null")].key, "smallest node of right sub-tree has to be next");
+            let ([next, max], right_height) = validate_rb_node::<UNIQUE, _, _, _>(right, tree, cmp);
+            assert!(cmp.compare(next, max).is_le(), "bad order");
+            assert!(ordered::<UNIQUE, _, _>(&node.key, next, cmp), "right tree overlap");
+            assert!(std::ptr::eq(next, &tree[node.order[1].expect("not null")].key), "smallest node of right sub-tree has to be next");
 
             assert_eq!(left_height, right_height, "black height of all paths has to be equal");
             ([min, max], left_height + (node.color as u8))
@@ -82,17 +90,34 @@ fn validate_rb_node<'a, K, V>(index: NodeIndex,
 }
 fn validate_rb_tree<K, V>(tree: &impl TreeReader<K, V>)
     where K: Ord + std::fmt::Debug, V: Value
+{
+    validate_rb_tree_with::<true, K, V, _>(tree, &OrdComparator)
+}
+fn validate_rb_multi<K, V>(tree: &impl TreeReader<K, V>)
+    where K: Ord + std::fmt::Debug, V: Value
+{
+    validate_rb_tree_with::<false, K, V, _>(tree, &OrdComparator)
+}
+fn validate_rb_tree_by<K, V, C>(tree: &impl TreeReader<K, V>, cmp: &C)
+    where K: std::fmt::Debug, V: Value, C: Comparator<K>
+{
+    validate_rb_tree_with::<true, K, V, C>(tree, cmp)
+}
+fn validate_rb_tree_with<const UNIQUE: bool, K, V, C>(tree: &impl TreeReader<K, V>, cmp: &C)
+    where K: std::fmt::Debug, V: Value, C: Comparator<K>
 {
     let meta = tree.meta();
     if let Some(root) = meta.root {
-        let ([min, max], black_height) = validate_rb_node(root, tree);
+        let ([min, max], black_height) = validate_rb_node::<UNIQUE, _, _, _>(root, tree, cmp);
         let min_node = &tree[meta.range[0].expect("not null")];
         let max_node = &tree[meta.range[1].expect("not null")];
-        assert_eq!(*min, min_node.key, "bad min range");
-        assert_eq!(*max, max_node.key, "bad max range");
+        assert!(std::ptr::eq(min, &min_node.key), "bad min range");
+        assert!(std::ptr::eq(max, &max_node.key), "bad max range");
         assert_eq!(meta.black_height, black_height, "tracked black-height and true black-height mismatch");
+        assert_eq!(meta.len, tree[root].size, "tracked length and true length mismatch");
     } else {
         assert_eq!(meta.range, [None, None], "empty tree implies empty range");
+        assert_eq!(meta.len, 0, "empty tree has no nodes");
         assert_eq!(meta.black_height, 0, "empty tree has no black nodes");
     }
 }
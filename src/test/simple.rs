@@ -15,7 +15,7 @@ fn insert_remove() {
         }
         for x in values.into_iter() {
             println!("==================== -{} ====================", x);
-            let value = alloc.remove(x);
+            let value = alloc.remove(&x);
             print_tree(&alloc.0);
             validate_rb_tree(&alloc.0);
             assert_eq!(value, Some(x));
@@ -23,6 +23,53 @@ fn insert_remove() {
     }
 }
 #[test]
+fn entry() {
+    let values = vec![1, 7, 8, 9, 1, 6, 5, 7, 3, 4, 1, 9];
+    let mut forest = SimpleWeakForest::new();
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for x in values.iter().copied() {
+            **alloc.entry(x).or_insert(0) += 1;
+            validate_rb_tree(&alloc.0);
+        }
+        assert_eq!(alloc.get(&1), Some(&3));
+        assert_eq!(alloc.get(&7), Some(&2));
+        assert_eq!(alloc.get(&8), Some(&1));
+        assert_eq!(alloc.get(&2), None);
+        match alloc.entry(7) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), (7, 2)),
+            Entry::Vacant(_) => panic!("key should exist")
+        }
+        validate_rb_tree(&alloc.0);
+        match alloc.entry(2) {
+            Entry::Vacant(entry) => assert_eq!(entry.into_key(), 2),
+            Entry::Occupied(_) => panic!("key should not exist")
+        }
+        assert_eq!(*alloc.entry(2).and_modify( |v| **v = 10 ).or_default(), &0);
+        assert_eq!(*alloc.entry(2).and_modify( |v| **v = 10 ).or_default(), &10);
+        let result = alloc.iter().map( |(k, _)| *k ).collect::<Vec<_>>();
+        assert_eq!(result, vec![1, 2, 3, 4, 5, 6, 8, 9]);
+    }
+}
+#[test]
+fn try_insert() {
+    let mut forest = SimpleWeakForest::with_capacity(4);
+    let mut tree = forest.insert();
+    let mut alloc = tree.alloc();
+    assert_eq!(alloc.insert(1, "a".to_string()), None);
+    assert_eq!(alloc.insert(1, "b".to_string()), Some("a".to_string()));
+    assert_eq!(alloc.insert(2, "c".to_string()), None);
+    **alloc.try_insert(3, "d".to_string()).expect("new key") += "e";
+    assert_eq!(alloc.get(&3).map( |v| v.as_str() ), Some("de"));
+    let Err(((key, value), Error::DuplicateKey)) = alloc.try_insert(2, "f".to_string())
+        else { panic!("expected duplicate key") };
+    assert_eq!((key, value.as_str()), (2, "f"));
+    assert_eq!(alloc.get(&2).map( |v| v.as_str() ), Some("c"));
+    assert_eq!(alloc.len(), 3);
+    validate_rb_tree(&alloc.0);
+}
+#[test]
 fn iter() {
     let mut values = vec![1, 7, 8, 9, 10, 6, 5, 2, 3, 4, 0, 11];
     let mut forest = SimpleWeakForest::with_capacity(values.len());
@@ -42,6 +89,264 @@ fn iter() {
     }
 }
 #[test]
+fn range() {
+    use std::ops::{Bound::{self, *}, RangeBounds};
+    const N: usize = 10;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    let tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (2 * n, n) )
+    ) };
+    let read = tree.read();
+    let bounds = [Unbounded, Included(0), Excluded(0), Included(7), Excluded(7), Included(8), Excluded(8), Included(18), Excluded(18), Included(25), Excluded(25)];
+    for lower in bounds {
+        for upper in bounds {
+            let range: (Bound<usize>, Bound<usize>) = (lower, upper);
+            let expected = (0..N).map( |n| 2 * n ).filter( |k| range.contains(k) ).collect::<Vec<_>>();
+            assert_eq!(read.range(range).map( |(k, _)| *k ).collect::<Vec<_>>(), expected);
+            assert_eq!(read.range(range).rev().map( |(k, _)| *k ).collect::<Vec<_>>(),
+                expected.iter().rev().copied().collect::<Vec<_>>()
+            );
+        }
+    }
+    assert_eq!(read.range(30..40).count(), 0);
+    assert_eq!(read.range(7..8).count(), 0);
+    assert_eq!(read.iter_range::<false, true>(&4, &10).map( |(k, _)| *k ).collect::<Vec<_>>(), vec![6, 8, 10]);
+    assert_eq!(read.key_range(), Some(&0..=&18));
+}
+#[test]
+fn iter_range_flags() {
+    const N: usize = 10;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    let tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (2 * n, n) )
+    ) };
+    let read = tree.read();
+    fn keys<'a>(iter: impl Iterator<Item = (&'a usize, &'a usize)>) -> Vec<usize> {
+        iter.map( |(k, _)| *k ).collect()
+    }
+    // NOTE: `true` includes the bound, this used to be the other way around
+    assert_eq!(keys(read.iter_range::<true, true>(&4, &10)), vec![4, 6, 8, 10]);
+    assert_eq!(keys(read.iter_range::<true, false>(&4, &10)), vec![4, 6, 8]);
+    assert_eq!(keys(read.iter_range::<false, true>(&4, &10)), vec![6, 8, 10]);
+    assert_eq!(keys(read.iter_range::<false, false>(&4, &10)), vec![6, 8]);
+    assert_eq!(keys(read.iter_range::<true, true>(&5, &9)), vec![6, 8]);
+    // NOTE: bounds outside of the tree used to fall back to the first and last node
+    assert_eq!(keys(read.iter_range::<true, true>(&20, &30)), vec![]);
+    assert_eq!(keys(read.iter_range::<true, true>(&30, &40)), vec![]);
+    assert_eq!(keys(read.iter_range::<false, false>(&0, &1)), vec![]);
+}
+#[test]
+fn borrow() {
+    use std::ops::Bound;
+    let words = ["apple", "banana", "cherry", "date", "elderberry"];
+    let mut forest = SimpleWeakForest::with_capacity(words.len());
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        words.iter().map( |w| (w.to_string(), w.len()) )
+    ) };
+    {
+        let mut alloc = tree.alloc();
+        assert_eq!(alloc.get("cherry"), Some(&6));
+        assert!(alloc.contains("date"));
+        assert!(!alloc.contains("fig"));
+        assert_eq!(alloc.rank("coconut"), Err(3));
+        **alloc.get_mut("apple").unwrap() += 1;
+        assert_eq!(alloc.get("apple"), Some(&6));
+        assert_eq!(alloc.range::<str, _>((Bound::Included("b"), Bound::Excluded("d"))).map( |(k, _)| k.as_str() ).collect::<Vec<_>>(), vec!["banana", "cherry"]);
+        assert_eq!(alloc.remove("banana"), Some(6));
+        validate_rb_tree(&alloc.0);
+    }
+    let (lower, pivot, upper) = tree.split("cherry");
+    assert_eq!(pivot, Some(6));
+    assert_eq!(lower.read().len(), 1);
+    assert_eq!(upper.read().len(), 2);
+}
+#[test]
+fn bounds() {
+    use std::ops::Bound::*;
+    use crate::tree::{CursorRead, CursorMove};
+    const N: usize = 10;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (2 * n, n) )
+    ) };
+    {
+        let read = tree.read();
+        assert_eq!(read.floor(&7), Some((&6, &3)));
+        assert_eq!(read.floor(&8), Some((&8, &4)));
+        assert_eq!(read.floor(&25), Some((&18, &9)));
+        assert_eq!(read.lower_bound(Excluded(&18)), None);
+        assert_eq!(read.ceiling(&7), Some((&8, &4)));
+        assert_eq!(read.ceiling(&8), Some((&8, &4)));
+        assert_eq!(read.ceiling(&19), None);
+        assert_eq!(read.lower_bound(Excluded(&8)), Some((&10, &5)));
+        assert_eq!(read.lower_bound(Included(&8)), Some((&8, &4)));
+        assert_eq!(read.lower_bound(Unbounded), Some((&0, &0)));
+        assert_eq!(read.upper_bound(Excluded(&8)), Some((&6, &3)));
+        assert_eq!(read.upper_bound(Excluded(&0)), None);
+        assert_eq!(read.upper_bound(Unbounded), Some((&18, &9)));
+        let mut cursor = read.cursor_lower_bound(Excluded(&8));
+        assert_eq!(cursor.key(), Some(&10));
+        cursor.move_next();
+        assert_eq!(cursor.key(), Some(&12));
+        let mut cursor = read.cursor_upper_bound(Included(&7));
+        assert_eq!(cursor.key(), Some(&6));
+        cursor.move_prev();
+        assert_eq!(cursor.key(), Some(&4));
+    }
+    {
+        let mut write = tree.write();
+        let cursor = write.cursor_mut_lower_bound(Included(&19));
+        assert_eq!(cursor.key(), None);
+        let cursor = write.cursor_mut_upper_bound(Excluded(&19));
+        assert_eq!(cursor.key(), Some(&18));
+    }
+}
+#[test]
+fn len() {
+    const N: usize = 10;
+    let mut forest = SimpleWeakForest::with_capacity(N << 1);
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for x in 0..N {
+            alloc.insert(x, x);
+            alloc.insert(x, x);
+            assert_eq!(alloc.len(), x + 1);
+        }
+        alloc.remove(&0);
+        alloc.remove(&0);
+        assert_eq!(alloc.len(), N - 1);
+    }
+    let (lower, pivot, upper) = tree.split(&4);
+    assert_eq!(pivot, Some(4));
+    assert_eq!(lower.read().len(), 3);
+    assert_eq!(upper.read().len(), N - 5);
+    let tree = upper.union_disjoint(lower).expect("disjoint trees");
+    assert_eq!(tree.read().len(), N - 2);
+    let other = unsafe { forest.insert_sorted_iter_unchecked(
+        (N..(N << 1)).map( |n| (n, n) )
+    ) };
+    assert_eq!(other.read().len(), N);
+    let mut tree = tree.union_merge(other, |_, _| panic!("duplicate key") ).unwrap();
+    {
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        assert_eq!(read.len(), (N << 1) - 2);
+    }
+    tree.alloc().clear();
+    assert_eq!(tree.read().len(), 0);
+    let tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, n) )
+    ) };
+    let mut iter = tree.into_iter();
+    assert_eq!(iter.len(), N);
+    iter.next();
+    iter.next_back();
+    assert_eq!(iter.len(), N - 2);
+    assert_eq!(iter.count(), N - 2);
+}
+#[test]
+fn rank_select() {
+    const N: usize = 20;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for x in (0..N).rev() {
+            alloc.insert(2 * x, x);
+        }
+        for x in 0..N {
+            assert_eq!(alloc.rank(&(2 * x)), Ok(x));
+            assert_eq!(alloc.rank(&(2 * x + 1)), Err(x + 1));
+            assert_eq!(alloc.select(x), Some((&(2 * x), &x)));
+            assert_eq!(alloc.key_at(x), Some(&(2 * x)));
+        }
+        assert_eq!(alloc.select(N), None);
+        alloc.remove(&0);
+        alloc.remove(&(2 * (N >> 1)));
+        assert_eq!(alloc.rank(&0), Err(0));
+        assert_eq!(alloc.rank(&2), Ok(0));
+        assert_eq!(alloc.rank(&(2 * (N - 1))), Ok(N - 3));
+        assert_eq!(alloc.key_at(N >> 1), Some(&(2 * (N >> 1) + 4)));
+    }
+    let (lower, _, upper) = tree.split(&(N - 1));
+    assert_eq!(lower.read().key_at(2), Some(&6));
+    assert_eq!(upper.read().rank(&(N + 6)), Ok(2));
+}
+#[test]
+fn retain() {
+    const N: usize = 100;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    for m in [7, 2] {
+        let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+            (0..N).map( |n| (n, n) )
+        ) };
+        {
+            let mut alloc = tree.alloc();
+            alloc.retain( |k, v| {
+                *v += 1;
+                k % m != 0
+            } );
+            validate_rb_tree(&alloc.0);
+            let expected = (0..N).filter( |k| k % m != 0 ).map( |k| (k, k + 1) ).collect::<Vec<_>>();
+            assert_eq!(alloc.iter().map( |(k, v)| (*k, *v) ).collect::<Vec<_>>(), expected);
+            alloc.retain( |k, _| k % 3 == 1 );
+            validate_rb_tree(&alloc.0);
+            let expected = (0..N).filter( |k| k % m != 0 && k % 3 == 1 ).collect::<Vec<_>>();
+            assert_eq!(alloc.iter().map( |(k, _)| *k ).collect::<Vec<_>>(), expected);
+        }
+    }
+}
+#[test]
+fn extract_if() {
+    const N: usize = 50;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, n) )
+    ) };
+    {
+        let mut alloc = tree.alloc();
+        let extracted = alloc.extract_if( |k, _| k % 3 == 0 ).collect::<Vec<_>>();
+        validate_rb_tree(&alloc.0);
+        assert_eq!(extracted, (0..N).filter( |k| k % 3 == 0 ).map( |k| (k, k) ).collect::<Vec<_>>());
+        assert_eq!(alloc.len(), N - extracted.len());
+        let first = alloc.extract_if( |k, _| k % 2 == 0 ).take(3).collect::<Vec<_>>();
+        validate_rb_tree(&alloc.0);
+        assert_eq!(first, vec![(2, 2), (4, 4), (8, 8)]);
+        assert!(alloc.contains(&10));
+        assert!(!alloc.contains(&4));
+    }
+}
+#[test]
+fn drain_range() {
+    const N: usize = 50;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |n| (n, n) )
+    ) };
+    {
+        let mut alloc = tree.alloc();
+        let drain = alloc.drain_range(10..20);
+        assert_eq!(drain.len(), 10);
+        assert_eq!(drain.collect::<Vec<_>>(), (10..20).map( |k| (k, k) ).collect::<Vec<_>>());
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.len(), N - 10);
+        let first = alloc.drain_range(5..=25).take(2).collect::<Vec<_>>();
+        assert_eq!(first, vec![(5, 5), (6, 6)]);
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.len(), N - 21);
+        assert_eq!(alloc.min(), Some(&0));
+        assert_eq!(alloc.key_at(5), Some(&26));
+        assert_eq!(alloc.remove_range(40..), 10);
+        assert_eq!(alloc.remove_range(30..30), 0);
+        assert_eq!(alloc.remove_range(..3), 3);
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.iter().map( |(k, _)| *k ).collect::<Vec<_>>(),
+            (3..5).chain(26..40).collect::<Vec<_>>()
+        );
+    }
+}
+#[test]
 fn union_disjoint() {
     const N: usize = 5;
     for i in 0..=N {
@@ -122,6 +427,355 @@ fn split() {
     }
 }
 #[test]
+fn split_range() {
+    use std::ops::{Bound::{self, *}, RangeBounds};
+    const N: usize = 20;
+    let bounds = [Unbounded, Included(0), Included(5), Excluded(5), Included(10), Excluded(10), Excluded(19), Included(25)];
+    for lower in bounds {
+        for upper in bounds {
+            let range: (Bound<usize>, Bound<usize>) = (lower, upper);
+            let mut forest = SimpleWeakForest::with_capacity(N);
+            let tree = unsafe { forest.insert_sorted_iter_unchecked(
+                (0..N).map( |n| (n, n) )
+            ) };
+            let (outer, inner) = tree.split_range(range);
+            let read = outer.read();
+            validate_rb_tree(&read.0);
+            assert_eq!(read.iter().map( |(k, _)| *k ).collect::<Vec<_>>(),
+                (0..N).filter( |k| !range.contains(k) ).collect::<Vec<_>>()
+            );
+            let read = inner.read();
+            validate_rb_tree(&read.0);
+            assert_eq!(read.iter().map( |(k, _)| *k ).collect::<Vec<_>>(),
+                (0..N).filter( |k| range.contains(k) ).collect::<Vec<_>>()
+            );
+        }
+    }
+}
+#[test]
+fn comparator() {
+    const N: usize = 10;
+    let mut forest = WeakForest::<usize, NoCumulant<usize>, _>::with_comparator( |a: &usize, b: &usize| b.cmp(a) );
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for x in [3, 8, 1, 0, 9, 5, 7, 2, 6, 4] {
+            alloc.insert(x, x);
+        }
+        assert_eq!(alloc.len(), N);
+        validate_rb_tree_by(&alloc.0, alloc.1.comparator());
+        assert_eq!(alloc.min(), Some(&(N - 1)));
+        assert_eq!(alloc.max(), Some(&0));
+        assert_eq!(alloc.get(&4), Some(&4));
+        assert_eq!(alloc.remove(&4), Some(4));
+        assert_eq!(alloc.get(&4), None);
+        assert_eq!(alloc.ceiling(&4), Some((&3, &3)));
+        assert_eq!(alloc.floor(&4), Some((&5, &5)));
+        assert_eq!(alloc.range(7..=3).map( |(k, _)| *k ).collect::<Vec<_>>(), vec![7, 6, 5, 3]);
+        alloc.insert(4, 4);
+    }
+    let keys = tree.read().iter().map( |(k, _)| *k ).collect::<Vec<_>>();
+    assert_eq!(keys, (0..N).rev().collect::<Vec<_>>());
+    let (left, pivot, right) = tree.split(&5);
+    assert_eq!(pivot, Some(5));
+    assert_eq!(left.read().iter().map( |(k, _)| *k ).collect::<Vec<_>>(), vec![9, 8, 7, 6]);
+    assert_eq!(right.read().iter().map( |(k, _)| *k ).collect::<Vec<_>>(), vec![4, 3, 2, 1, 0]);
+    validate_rb_tree_by(&left.read().0, left.comparator());
+    validate_rb_tree_by(&right.read().0, right.comparator());
+    let tree = right.union_disjoint(left).ok().unwrap();
+    let read = tree.read();
+    validate_rb_tree_by(&read.0, read.1.comparator());
+    assert_eq!(read.len(), N - 1);
+    assert_eq!(read.min(), Some(&9));
+}
+#[test]
+fn comparator_case_insensitive() {
+    let cmp = |a: &String, b: &String| a.to_lowercase().cmp(&b.to_lowercase());
+    let mut forest = WeakForest::<String, NoCumulant<usize>, _>::with_comparator(cmp);
+    let mut tree = forest.insert();
+    let mut other = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for (i, key) in ["delta", "Alpha", "charlie", "Echo", "bravo"].into_iter().enumerate() {
+            alloc.insert(key.to_string(), i);
+        }
+        assert_eq!(alloc.insert("ALPHA".to_string(), 10), Some(1));
+        validate_rb_tree_by(&alloc.0, alloc.1.comparator());
+        assert_eq!(alloc.len(), 5);
+        assert_eq!(alloc.get(&"CHARLIE".to_string()), Some(&2));
+        assert_eq!(alloc.iter().map( |(k, _)| k.to_lowercase() ).collect::<Vec<_>>(),
+            vec!["alpha", "bravo", "charlie", "delta", "echo"]);
+    }
+    {
+        let mut alloc = other.alloc();
+        for (i, key) in ["BRAVO", "Foxtrot", "golf"].into_iter().enumerate() {
+            alloc.insert(key.to_string(), 100 + i);
+        }
+    }
+    let tree = tree.union_merge(other, |a, b| **a += Value::into(b) ).ok().unwrap();
+    let read = tree.read();
+    validate_rb_tree_by(&read.0, read.1.comparator());
+    assert_eq!(read.len(), 7);
+    assert_eq!(read.get(&"bravo".to_string()), Some(&104));
+}
+#[test]
+fn foreign_forest() {
+    let mut forest = SimpleWeakForest::new();
+    let mut other = SimpleWeakForest::new();
+    let tree = unsafe { forest.insert_sorted_iter_unchecked((0..5).map( |i| (i, i) )) };
+    let foreign = unsafe { other.insert_sorted_iter_unchecked((10..15).map( |i| (i, i) )) };
+    let ((tree, foreign), err) = tree.union_disjoint(foreign).unwrap_err();
+    assert!(matches!(err, Error::ForeignTree));
+    let ((tree, foreign), err) = tree.union_merge(foreign, |_, _| ()).unwrap_err();
+    assert!(matches!(err, Error::ForeignTree));
+    let ((tree, foreign), err) = tree.intersection(foreign, |_, _| ()).unwrap_err();
+    assert!(matches!(err, Error::ForeignTree));
+    let ((tree, foreign), err) = tree.difference(foreign).unwrap_err();
+    assert!(matches!(err, Error::ForeignTree));
+    let ((tree, foreign), err) = tree.symmetric_difference(foreign).unwrap_err();
+    assert!(matches!(err, Error::ForeignTree));
+    assert_eq!(tree.read().len(), 5);
+    assert_eq!(foreign.read().len(), 5);
+}
+#[test]
+fn union_merge_direction() {
+    let mut forest = SimpleWeakForest::new();
+    for (this, other) in [(0..10, 5..15), (3..4, 0..10), (0..10, 7..8), (3..4, 3..4)] {
+        let expected = this.clone().chain(other.clone())
+            .map( |k| (k, if this.contains(&k) && other.contains(&k) { 12 } else if this.contains(&k) { 1 } else { 2 }) )
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let tree = unsafe { forest.insert_sorted_iter_unchecked(this.map( |k| (k, 1) )) };
+        let other = unsafe { forest.insert_sorted_iter_unchecked(other.map( |k| (k, 2) )) };
+        let tree = tree.union_merge(other, |a, b| **a = **a * 10 + Value::into(b) ).unwrap();
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        assert_eq!(read.iter().map( |(k, v)| (*k, *v) ).collect::<Vec<_>>(), expected.into_iter().collect::<Vec<_>>());
+    }
+}
+#[test]
+fn multi() {
+    let keys = vec![3, 1, 3, 2, 3, 1, 0, 2, 3];
+    let mut forest = SimpleMultiWeakForest::new();
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for (i, k) in keys.iter().copied().enumerate() {
+            alloc.insert(k, i);
+            validate_rb_multi(&alloc.0.0);
+        }
+        assert_eq!(alloc.len(), keys.len());
+        assert_eq!(alloc.count(&3), 4);
+        assert_eq!(alloc.count(&4), 0);
+        assert_eq!(alloc.get_all(&3).map( |(_, v)| *v ).collect::<Vec<_>>(), vec![0, 2, 4, 8]);
+        assert_eq!(alloc.range(1..3).map( |(_, v)| *v ).collect::<Vec<_>>(), vec![1, 5, 3, 7]);
+        assert_eq!(alloc.rank(&2), Ok(3));
+        assert_eq!(alloc.remove_one(&3), Some(0));
+        assert_eq!(alloc.remove_one(&4), None);
+        assert_eq!(alloc.remove_all(&1), 2);
+        validate_rb_multi(&alloc.0.0);
+        assert_eq!(alloc.iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![6, 3, 7, 2, 4, 8]);
+    }
+    let mut other = forest.insert();
+    {
+        let mut alloc = other.alloc();
+        for (i, k) in [2, 3, 4, 2].into_iter().enumerate() {
+            alloc.insert(k, 10 + i);
+        }
+    }
+    let tree = tree.union(other).unwrap();
+    validate_rb_multi(&tree.read().0.0);
+    assert_eq!(tree.read().iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![6, 3, 7, 10, 13, 2, 4, 8, 11, 12]);
+    let (left, center, right) = tree.split(&3);
+    validate_rb_multi(&left.read().0.0);
+    validate_rb_multi(&center.read().0.0);
+    validate_rb_multi(&right.read().0.0);
+    assert_eq!(left.read().iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![6, 3, 7, 10, 13]);
+    assert_eq!(center.read().iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![2, 4, 8, 11]);
+    assert_eq!(right.read().iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![12]);
+    let mut tree = center.union(left).unwrap();
+    {
+        let read = tree.read();
+        assert_eq!(read.count(&2), 4);
+        assert_eq!(read.get_all(&3).map( |(_, v)| *v ).collect::<Vec<_>>(), vec![2, 4, 8, 11]);
+    }
+    {
+        let mut alloc = tree.alloc();
+        assert_eq!(alloc.pop_first(), Some((0, 6)));
+        assert_eq!(alloc.pop_last(), Some((3, 11)));
+        alloc.retain( |_, v| *v % 2 == 0 );
+        validate_rb_multi(&alloc.0.0);
+        assert_eq!(alloc.iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![10, 2, 4, 8]);
+        let mut write = alloc.downgrade();
+        for (_, v) in write.iter_mut() {
+            *v += 1;
+        }
+        assert_eq!(write.get_all(&3).map( |(_, v)| *v ).collect::<Vec<_>>(), vec![3, 5, 9]);
+    }
+}
+#[test]
+fn count_range() {
+    use std::ops::Bound;
+    const N: usize = 100;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    let tree = unsafe { forest.insert_sorted_iter_unchecked(
+        (0..N).map( |i| (i << 1, i) )
+    ) };
+    {
+        let read = tree.read();
+        assert_eq!(read.count_range(..), N);
+        assert_eq!(read.count_range(10..20), 5);
+        assert_eq!(read.count_range(10..=20), 6);
+        assert_eq!(read.count_range(11..=19), 4);
+        assert_eq!(read.count_range((Bound::Excluded(10), Bound::Excluded(20))), 4);
+        assert_eq!(read.count_range(..N), N >> 1);
+        assert_eq!(read.count_range(N << 1..), 0);
+        assert_eq!(read.count_range(20..10), 0);
+        for (a, b) in [(0, 0), (3, 77), (51, 52), (150, 199)] {
+            assert_eq!(read.count_range(a..b), read.range(a..b).count());
+        }
+    }
+}
+#[test]
+fn prefix() {
+    let mut forest = SimpleWeakForest::<Vec<u8>, usize>::new();
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for (i, key) in [&b"a"[..], b"ab", b"ab\xff", b"ab\xff\x00", b"ac", b"b", b"\xff", b"\xff\xff", b"\xff\xff\x01"].into_iter().enumerate() {
+            alloc.insert(key.to_vec(), i);
+        }
+        let values = |prefix: &[u8]| alloc.iter_prefix(prefix).map( |(_, v)| *v ).collect::<Vec<_>>();
+        assert_eq!(values(b"a"), vec![0, 1, 2, 3, 4]);
+        assert_eq!(values(b"ab"), vec![1, 2, 3]);
+        assert_eq!(values(b"ab\xff"), vec![2, 3]);
+        assert_eq!(values(b"\xff"), vec![6, 7, 8]);
+        assert_eq!(values(b"\xff\xff"), vec![7, 8]);
+        assert_eq!(values(b""), (0..9).collect::<Vec<_>>());
+        assert_eq!(values(b"c"), vec![]);
+    }
+    let (rest, inner) = tree.split_prefix(b"\xff");
+    validate_rb_tree(&rest.read().0);
+    validate_rb_tree(&inner.read().0);
+    assert_eq!(rest.read().len(), 6);
+    assert_eq!(inner.read().iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![6, 7, 8]);
+
+    let mut forest = SimpleWeakForest::<String, usize>::new();
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for (i, key) in ["foo", "foo/bar", "foo/baz", "foo0", "fop", "\u{D7FF}", "\u{D7FF}x", "\u{E000}"].into_iter().enumerate() {
+            alloc.insert(key.to_owned(), i);
+        }
+        let values = |prefix: &str| alloc.iter_prefix_str(prefix).map( |(_, v)| *v ).collect::<Vec<_>>();
+        assert_eq!(values("foo/"), vec![1, 2]);
+        assert_eq!(values("fo"), vec![0, 1, 2, 3, 4]);
+        assert_eq!(values("\u{D7FF}"), vec![5, 6]);
+    }
+    let (rest, inner) = tree.split_prefix_str("foo");
+    validate_rb_tree(&rest.read().0);
+    validate_rb_tree(&inner.read().0);
+    assert_eq!(rest.read().iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![4, 5, 6, 7]);
+    assert_eq!(inner.read().iter().map( |(_, v)| *v ).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+}
+#[test]
+fn split_at() {
+    const N: usize = 20;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    for n in [0, 1, 7, N - 1, N, N + 5] {
+        let tree = unsafe { forest.insert_sorted_iter_unchecked((0..N).map( |i| (i, i) )) };
+        let (left, right) = tree.split_at(n);
+        validate_rb_tree(&left.read().0);
+        validate_rb_tree(&right.read().0);
+        assert_eq!(left.read().iter().map( |(k, _)| *k ).collect::<Vec<_>>(), (0..n.min(N)).collect::<Vec<_>>());
+        assert_eq!(right.read().iter().map( |(k, _)| *k ).collect::<Vec<_>>(), (n.min(N)..N).collect::<Vec<_>>());
+    }
+    let mut tree = unsafe { forest.insert_sorted_iter_unchecked((0..N).map( |i| (i, i) )) };
+    {
+        let mut alloc = tree.alloc();
+        alloc.truncate(N + 1);
+        assert_eq!(alloc.len(), N);
+        alloc.truncate(5);
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.iter().map( |(k, _)| *k ).collect::<Vec<_>>(), (0..5).collect::<Vec<_>>());
+        alloc.truncate(0);
+        assert!(alloc.is_empty());
+    }
+    for n in 0..=N {
+        let mut tree = unsafe { forest.insert_sorted_iter_unchecked((0..N).map( |i| (i, i) )) };
+        let mut alloc = tree.alloc();
+        alloc.truncate(n);
+        validate_rb_tree(&alloc.0);
+        assert_eq!(alloc.iter().map( |(k, _)| *k ).collect::<Vec<_>>(), (0..n).collect::<Vec<_>>());
+        alloc.clear();
+    }
+}
+#[test]
+fn set_algebra() {
+    const N: usize = 30;
+    let mut forest = SimpleWeakForest::with_capacity(N << 1);
+    let mut trees = || {
+        let a = unsafe { forest.insert_sorted_iter_unchecked((0..N).step_by(2).map( |i| (i, i) )) };
+        let b = unsafe { forest.insert_sorted_iter_unchecked((0..N).step_by(3).map( |i| (i, 100) )) };
+        (a, b)
+    };
+    let keys = |tree: &Tree<usize, NoCumulant<usize>>| {
+        validate_rb_tree(&tree.read().0);
+        tree.read().iter().map( |(k, _)| *k ).collect::<Vec<_>>()
+    };
+    let (a, b) = trees();
+    let tree = a.intersection(b, |a, b| **a += Value::into(b)).unwrap();
+    assert_eq!(keys(&tree), (0..N).step_by(6).collect::<Vec<_>>());
+    assert!(tree.read().iter().all( |(k, v)| *v == k + 100 ));
+    let (a, b) = trees();
+    let tree = a.difference(b).unwrap();
+    assert_eq!(keys(&tree), (0..N).filter( |i| i % 2 == 0 && i % 3 != 0 ).collect::<Vec<_>>());
+    let (a, b) = trees();
+    let tree = a.symmetric_difference(b).unwrap();
+    assert_eq!(keys(&tree), (0..N).filter( |i| (i % 2 == 0) != (i % 3 == 0) ).collect::<Vec<_>>());
+    let (a, b) = trees();
+    let (empty, _, _) = b.split(&0);
+    assert_eq!(keys(&a.intersection(empty, |_, _| ()).unwrap()), vec![]);
+}
+#[test]
+fn nearest() {
+    let mut forest = SimpleWeakForest::new();
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for x in [10i64, 20, 25, 40, 41] {
+            alloc.insert(x, x);
+        }
+        let dist = |a: &i64, b: &i64| (a - b).abs();
+        assert_eq!(alloc.nearest(&0, dist), Some((&10, &10)));
+        assert_eq!(alloc.nearest(&22, dist), Some((&20, &20)));
+        assert_eq!(alloc.nearest(&23, dist), Some((&25, &25)));
+        assert_eq!(alloc.nearest(&40, dist), Some((&40, &40)));
+        assert_eq!(alloc.nearest_abs(&100), Some((&41, &41)));
+        assert_eq!(alloc.nearest_abs(&15), Some((&10, &10)));
+        let keys = |k| alloc.k_nearest(&30, k, dist).into_iter().map( |(k, _)| *k ).collect::<Vec<_>>();
+        assert_eq!(keys(0), vec![]);
+        assert_eq!(keys(3), vec![25, 20, 40]);
+        assert_eq!(keys(10), vec![25, 20, 40, 41, 10]);
+        alloc.clear();
+        assert_eq!(alloc.nearest_abs(&0), None);
+        assert_eq!(alloc.nearest(&0, dist), None);
+        alloc.insert(5, 5);
+        assert_eq!(alloc.nearest(&0, dist), Some((&5, &5)));
+        assert_eq!(alloc.nearest(&9, dist), Some((&5, &5)));
+    }
+    let mut forest = SimpleWeakForest::new();
+    let mut tree = forest.insert();
+    {
+        let mut alloc = tree.alloc();
+        for x in [10i64, 20, 30] {
+            alloc.insert(Box::new(x), x);
+        }
+        assert_eq!(alloc.nearest(&24i64, |a, b| (a - b).abs()).map( |(_, v)| *v ), Some(20));
+        assert_eq!(alloc.nearest_abs(&26i64).map( |(_, v)| *v ), Some(30));
+        assert_eq!(alloc.k_nearest(&0i64, 2, |a, b| (a - b).abs()).len(), 2);
+    }
+}
+#[test]
 fn union() {
     const N: usize = 10;
     let mut forest = SimpleWeakForest::with_capacity(N << 1);
@@ -141,7 +795,7 @@ fn union() {
         print_tree(&read.0);
         validate_rb_tree(&read.0);
     }
-    let all = odd.union_merge(even, |_, _| panic!("duplicate key") );
+    let all = odd.union_merge(even, |_, _| panic!("duplicate key") ).unwrap();
     {
         let read = all.read();
         print_tree(&read.0);
@@ -204,4 +858,114 @@ fn filter() {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+fn split_many() {
+    const N: usize = 50;
+    let mut forest = SimpleWeakForest::with_capacity(N);
+    let keys = [0, 3, 4, 10, 11, 25, 49, 60];
+    let tree = unsafe { forest.insert_sorted_iter_unchecked((0..N).map( |i| (i, i) )) };
+    let trees = tree.split_many(&keys);
+    assert_eq!(trees.len(), keys.len() + 1);
+    let bounds = std::iter::once(0).chain(keys.iter().map( |k| (*k).min(N) )).chain(std::iter::once(N)).collect::<Vec<_>>();
+    for (tree, range) in trees.iter().zip(bounds.windows(2)) {
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        assert_eq!(read.iter().map( |(k, _)| *k ).collect::<Vec<_>>(), (range[0]..range[1]).collect::<Vec<_>>());
+    }
+    let tree = Tree::concat(trees).unwrap().unwrap();
+    {
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        assert_eq!(read.iter().map( |(k, _)| *k ).collect::<Vec<_>>(), (0..N).collect::<Vec<_>>());
+    }
+    let mut trees = tree.split_many(&[20, 30]);
+    trees.swap(0, 2);
+    let (mut trees, err) = Tree::concat(trees).unwrap_err();
+    assert!(matches!(err, Error::Overlapping));
+    trees.push(SimpleWeakForest::new().insert());
+    trees.swap(0, 2);
+    let (_, err) = Tree::concat(trees).unwrap_err();
+    assert!(matches!(err, Error::ForeignTree));
+    assert!(Tree::<usize, NoCumulant<usize>>::concat(Vec::new()).unwrap().is_none());
+    let tree = forest.insert();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe( || tree.split_many(&[3, 3]) ));
+    assert!(result.is_err());
+}
+#[test]
+fn union_all() {
+    const N: usize = 60;
+    let mut forest = SimpleWeakForest::with_capacity(N << 2);
+    assert!(Tree::<usize, NoCumulant<usize>>::union_all(Vec::new(), |_, _| ()).unwrap().is_none());
+    let trees = (1..8).map( |step| unsafe {
+        forest.insert_sorted_iter_unchecked((0..N).step_by(step).map( |i| (i, 1) ))
+    } ).collect::<Vec<_>>();
+    let mut merges = 0;
+    let tree = Tree::union_all(trees, |a, b| {
+        merges += 1;
+        **a += Value::into(b);
+    } ).unwrap().unwrap();
+    {
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        let expected = (0..N)
+            .map( |i| (i, (1..8).filter( |step| i % step == 0 ).count()) )
+            .collect::<Vec<_>>();
+        assert_eq!(merges, expected.iter().map( |(_, count)| count - 1 ).sum::<usize>());
+        assert_eq!(read.iter().map( |(k, v)| (*k, *v) ).collect::<Vec<_>>(), expected);
+    }
+    let trees = (0..5).map( |n| unsafe {
+        forest.insert_sorted_iter_unchecked((n * 10..n * 10 + 10).map( |i| (i, i) ))
+    } ).collect::<Vec<_>>();
+    let tree = Tree::union_all(trees, |_, _| panic!("trees are disjoint") ).unwrap().unwrap();
+    {
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        assert_eq!(read.iter().map( |(k, _)| *k ).collect::<Vec<_>>(), (0..50).collect::<Vec<_>>());
+    }
+    let foreign = unsafe { SimpleWeakForest::new().insert_sorted_iter_unchecked((0..5).map( |i| (i, i) )) };
+    let (trees, err) = Tree::union_all([tree, foreign], |_, _| ()).unwrap_err();
+    assert!(matches!(err, Error::ForeignTree));
+    assert_eq!(trees.len(), 2);
+    assert_eq!(trees[0].read().len(), 50);
+}
+#[test]
+fn join_black_height() {
+    const N: usize = 40;
+    let mut forest = SimpleWeakForest::with_capacity(N << 1);
+    let scrambled = |n: usize| (0..n).step_by(2).chain((1..n).step_by(2)).collect::<Vec<_>>();
+    for a in 0..N {
+        for b in [0, 1, 2, 3, 5, 8, 13, 21, 34] {
+            // NOTE: inserting out of order leaves red nodes along the spines of both trees
+            let mut left = forest.insert();
+            let mut right = forest.insert();
+            for i in scrambled(a) {
+                left.alloc().insert(i, i);
+            }
+            for i in scrambled(b) {
+                right.alloc().insert(N + 1 + i, i);
+            }
+            let tree = left.join_with(N, 0, right).unwrap();
+            let read = tree.read();
+            validate_rb_tree(&read.0);
+            assert_eq!(read.len(), a + b + 1);
+        }
+    }
+}
+#[test]
+fn sorted_owned() {
+    const N: usize = 20;
+    // NOTE: the items are moved into the tree, so they would be freed twice if the source dropped them as well
+    let items = || (0..N).map( |n| (format!("{n:02}"), n.to_string()) );
+    let mut forest = SimpleWeakForest::with_capacity(N << 1);
+    let tree = unsafe { forest.insert_sorted_iter_unchecked(items()) };
+    {
+        let read = tree.read();
+        validate_rb_tree(&read.0);
+        assert_eq!(read.get("07").map( String::as_str ), Some("7"));
+    }
+    assert_eq!(tree.into_iter().collect::<Vec<_>>(), items().collect::<Vec<_>>());
+    let slice = items().collect::<Vec<_>>();
+    let tree = unsafe { forest.insert_sorted_slice_unchecked(&slice) };
+    assert_eq!(tree.into_iter().collect::<Vec<_>>(), slice);
+}
@@ -0,0 +1,45 @@
+use std::{
+    cmp::Ordering,
+    ops::{Bound, RangeBounds}
+};
+
+/// Defines the order of the keys in a [Tree](crate::tree::Tree).
+///
+/// The comparator is stored once per [WeakForest](crate::WeakForest) and shared by all of its trees,
+/// so it can hold state like a runtime-chosen collation.
+pub trait Comparator<T: ?Sized> {
+    /// Compares two keys, this has to be a total order.
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+/// This type orders keys using their [Ord] implementation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrdComparator;
+impl<T: Ord + ?Sized> Comparator<T> for OrdComparator {
+    #[inline(always)]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Comparator<T> for F {
+    #[inline(always)]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+/// Returns `true` when the key is inside of the range, using the order defined by the comparator.
+#[inline]
+pub(crate) fn range_contains<T, R, C>(range: &R, key: &T, cmp: &C) -> bool
+    where T: ?Sized, R: RangeBounds<T>, C: Comparator<T>
+{
+    let lower = match range.start_bound() {
+        Bound::Included(start) => cmp.compare(start, key).is_le(),
+        Bound::Excluded(start) => cmp.compare(start, key).is_lt(),
+        Bound::Unbounded => true
+    };
+    let upper = match range.end_bound() {
+        Bound::Included(end) => cmp.compare(key, end).is_le(),
+        Bound::Excluded(end) => cmp.compare(key, end).is_lt(),
+        Bound::Unbounded => true
+    };
+    lower && upper
+}
@@ -1,9 +1,9 @@
-use std::marker::PhantomData;
+use std::{borrow::Borrow, marker::PhantomData, ops::Bound};
 
 use crate::{
     arena::{Meta, PortAllocGuard},
     tree::{
-        Tree, Bounds, Node, NodeRef, Value, SearchResult,
+        Tree, Bounds, Node, NodeRef, Value, SearchResult, Comparator,
         Iter, IterMut,
         TreeReader, TreeWriter,
         TreeReadGuard, TreeWriteGuard, TreeAllocGuard
@@ -11,28 +11,28 @@ use crate::{
 };
 /// Read-only cursor for a [Tree].
 #[derive(Debug, Clone)]
-pub struct Cursor<'a, K: Ord, V, R: TreeReader<K, V>> {
+pub struct Cursor<'a, K, V, R: TreeReader<K, V>> {
     tree: &'a R,
     current: NodeRef,
     _phantom: PhantomData<(K, V)>
 }
 /// Read-write cursor for a [Tree].
 #[derive(Debug)]
-pub struct CursorMut<'a, K: Ord, V, W: TreeWriter<K, V>> {
+pub struct CursorMut<'a, K, V, W: TreeWriter<K, V>> {
     tree: &'a mut W,
     current: NodeRef,
     _phantom: PhantomData<(K, V)>
 }
 /// Read-write cursor for a [Tree] with option to insert/remove elements.
 #[derive(Debug)]
-pub struct CursorAlloc<'a, 'b, K: Ord, V: Value> {
+pub struct CursorAlloc<'a, 'b, K, V: Value> {
     tree: &'a mut PortAllocGuard<'b, Node<K, V>, Bounds>,
     current: NodeRef
 }
 
 macro_rules! impl_Cursor {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns a read-only cursor.
             #[inline]
             pub fn cursor(&self) -> Cursor<K, V, impl TreeReader<K, V> + 'a> {
@@ -40,8 +40,10 @@ macro_rules! impl_Cursor {
             }
             /// Returns a read-only cursor starting at the node at/next to given key.
             #[inline]
-            pub fn cursor_at(&self, key: &K) -> Cursor<K, V, impl TreeReader<K, V> + 'a> {
-                let current = match unsafe { Tree::search(self.0.meta().root, key, &self.0) } {
+            pub fn cursor_at<Q>(&self, key: &Q) -> Cursor<K, V, impl TreeReader<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let current = match unsafe { Tree::search(self.0.meta().root, key, self.1.comparator(), &self.0) } {
                     SearchResult::Here(ptr) => Some(ptr),
                     SearchResult::LeftOf(ptr) => self.0[ptr].order[0],
                     SearchResult::RightOf(ptr) => Some(ptr),
@@ -49,6 +51,22 @@ macro_rules! impl_Cursor {
                 };
                 Cursor { tree: &self.0, current, _phantom: PhantomData }
             }
+            /// Returns a read-only cursor starting at the lowest node above the bound.
+            #[inline]
+            pub fn cursor_lower_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<K, V, impl TreeReader<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let current = Tree::closest_bound::<1, _, _>(bound, self.1.comparator(), &self.0);
+                Cursor { tree: &self.0, current, _phantom: PhantomData }
+            }
+            /// Returns a read-only cursor starting at the biggest node below the bound.
+            #[inline]
+            pub fn cursor_upper_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<K, V, impl TreeReader<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let current = Tree::closest_bound::<0, _, _>(bound, self.1.comparator(), &self.0);
+                Cursor { tree: &self.0, current, _phantom: PhantomData }
+            }
         }
     };
 }
@@ -58,7 +76,7 @@ impl_Cursor!(TreeAllocGuard);
 
 macro_rules! impl_CursorMut {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns a read-write cursor.
             #[inline]
             pub fn cursor_mut(&mut self) -> CursorMut<K, V, impl TreeWriter<K, V> + 'a> {
@@ -67,8 +85,10 @@ macro_rules! impl_CursorMut {
             }
             /// Returns a read-write cursor starting at the node at/next to given key.
             #[inline]
-            pub fn cursor_mut_at(&mut self, key: &K) -> CursorMut<K, V, impl TreeWriter<K, V> + 'a> {
-                let current = match unsafe { Tree::search(self.0.meta().root, key, &self.0) } {
+            pub fn cursor_mut_at<Q>(&mut self, key: &Q) -> CursorMut<K, V, impl TreeWriter<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let current = match unsafe { Tree::search(self.0.meta().root, key, self.1.comparator(), &self.0) } {
                     SearchResult::Here(ptr) => Some(ptr),
                     SearchResult::LeftOf(ptr) => self.0[ptr].order[0],
                     SearchResult::RightOf(ptr) => Some(ptr),
@@ -76,13 +96,29 @@ macro_rules! impl_CursorMut {
                 };
                 CursorMut { tree: &mut self.0, current, _phantom: PhantomData }
             }
+            /// Returns a read-write cursor starting at the lowest node above the bound.
+            #[inline]
+            pub fn cursor_mut_lower_bound<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<K, V, impl TreeWriter<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let current = Tree::closest_bound::<1, _, _>(bound, self.1.comparator(), &self.0);
+                CursorMut { tree: &mut self.0, current, _phantom: PhantomData }
+            }
+            /// Returns a read-write cursor starting at the biggest node below the bound.
+            #[inline]
+            pub fn cursor_mut_upper_bound<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<K, V, impl TreeWriter<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let current = Tree::closest_bound::<0, _, _>(bound, self.1.comparator(), &self.0);
+                CursorMut { tree: &mut self.0, current, _phantom: PhantomData }
+            }
         }
     };
 }
 impl_CursorMut!(TreeWriteGuard);
 impl_CursorMut!(TreeAllocGuard);
 
-impl<'a, K: Ord, V: Value> TreeAllocGuard<'a, K, V> {
+impl<'a, K, V: Value, C> TreeAllocGuard<'a, K, V, C> {
     /// Returns a read-write cursor with option to insert/remove elements.
     #[inline]
     pub fn cursor_alloc(&mut self) -> CursorAlloc<'_, 'a, K, V> {
@@ -91,8 +127,10 @@ impl<'a, K: Ord, V: Value> TreeAllocGuard<'a, K, V> {
     }
     /// Returns a read-write cursor starting at the node at/next to given key with option to insert/remove elements.
     #[inline]
-    pub fn cursor_alloc_at(&mut self, key: &K) -> CursorAlloc<'_, 'a, K, V> {
-        let current = match unsafe { Tree::search(self.0.meta().root, key, &self.0) } {
+    pub fn cursor_alloc_at<Q>(&mut self, key: &Q) -> CursorAlloc<'_, 'a, K, V>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
+        let current = match unsafe { Tree::search(self.0.meta().root, key, self.1.comparator(), &self.0) } {
             SearchResult::Here(ptr) => Some(ptr),
             SearchResult::LeftOf(ptr) => self.0[ptr].order[0],
             SearchResult::RightOf(ptr) => Some(ptr),
@@ -126,7 +164,7 @@ pub trait CursorMove {
 
 macro_rules! impl_CursorMove {
     ( $type:ident ; $( $pre:lifetime ),* ; $( $post:ident : $postcond:path ),*) => {
-        impl<'a, $( $pre , )* K: Ord, V: Value, $( $post : $postcond ),* > CursorMove for $type <'a, $( $pre , )* K, V, $( $post ),* > {
+        impl<'a, $( $pre , )* K, V: Value, $( $post : $postcond ),* > CursorMove for $type <'a, $( $pre , )* K, V, $( $post ),* > {
             #[inline]
             fn move_order<const I: usize>(&mut self)
                 where [(); 1 - I]:
@@ -165,7 +203,7 @@ pub trait CursorRead<K, V> {
 
 macro_rules! impl_CursorRead {
     ( $type:ident ; $( $pre:lifetime ),* ; $( $post:ident : $postcond:path ),*) => {
-        impl<'a, $( $pre , )* K: Ord, V: Value, $( $post : $postcond ),* > CursorRead<K, V> for $type <'a, $( $pre , )* K, V, $( $post ),* > {
+        impl<'a, $( $pre , )* K, V: Value, $( $post : $postcond ),* > CursorRead<K, V> for $type <'a, $( $pre , )* K, V, $( $post ),* > {
             #[inline]
             fn key(&self) -> Option<&K> {
                 Some(&self.tree[self.current?].key)
@@ -209,7 +247,7 @@ pub trait CursorPeek<K, V>: CursorMove + CursorRead<K, V> {
 }
 macro_rules! impl_CursorPeek {
     ( $type:ident ; $( $pre:lifetime ),* ; $( $post:ident : $postcond:path ),*) => {
-        impl<'a, $( $pre , )* K: Ord, V: Value, $( $post : $postcond ),* > CursorPeek<K, V> for $type <'a, $( $pre , )* K, V, $( $post ),* > {
+        impl<'a, $( $pre , )* K, V: Value, $( $post : $postcond ),* > CursorPeek<K, V> for $type <'a, $( $pre , )* K, V, $( $post ),* > {
             #[inline]
             fn peek_order<const I: usize>(&self) -> Option<(&K, &V)>
                 where [(); 1 - I]:
@@ -248,7 +286,7 @@ pub trait CursorWrite<K, V>: CursorRead<K, V> {
 }
 macro_rules! impl_CursorWrite {
     ( $type:ident ; $( $pre:lifetime ),* ; $( $post:ident : $postcond:path ),*) => {
-        impl<'a, $( $pre , )* K: Ord, V: Value, $( $post : $postcond ),* > CursorWrite<K, V> for $type <'a, $( $pre , )* K, V, $( $post ),* > {
+        impl<'a, $( $pre , )* K, V: Value, $( $post : $postcond ),* > CursorWrite<K, V> for $type <'a, $( $pre , )* K, V, $( $post ),* > {
             #[inline]
             fn value_mut(&mut self) -> Option<&mut V> {
                 Some(&mut self.tree[self.current?].value)
@@ -259,7 +297,7 @@ macro_rules! impl_CursorWrite {
 impl_CursorWrite!(CursorMut;; W: TreeWriter<K, V>);
 impl_CursorWrite!(CursorAlloc; 'b;);
 
-impl<'a, 'b, K: Ord, V: Value> CursorAlloc<'a, 'b, K, V> {
+impl<'a, 'b, K, V: Value> CursorAlloc<'a, 'b, K, V> {
     #[inline]
     pub fn remove_order<const I: usize>(&mut self) -> Option<(K, V)>
         where [(); 1 - I]:
@@ -304,7 +342,7 @@ impl<'a, 'b, K: Ord, V: Value> CursorAlloc<'a, 'b, K, V> {
 
 macro_rules! impl_Iter {
     ( $type:ident ; $( $pre:lifetime ),* ; $( $post:ident : $postcond:path ),*) => {
-        impl<'a, $( $pre , )* K: Ord, V: Value, $( $post : $postcond ),* > $type <'a, $( $pre , )* K, V, $( $post ),* > {
+        impl<'a, $( $pre , )* K, V: Value, $( $post : $postcond ),* > $type <'a, $( $pre , )* K, V, $( $post ),* > {
             #[inline]
             pub fn iter_below(&self) -> Iter<K, V, impl TreeReader<K, V> + 'a> {
                 let [front, back] = if let Some(current) = self.current {
@@ -324,7 +362,7 @@ impl_Iter!(CursorAlloc; 'b;);
 
 macro_rules! impl_IterMut {
     ( $type:ident ; $( $pre:lifetime ),* ; $( $post:ident : $postcond:path ),*) => {
-        impl<'a, $( $pre , )* K: Ord, V: Value, $( $post : $postcond ),* > $type <'a, $( $pre , )* K, V, $( $post ),* > {
+        impl<'a, $( $pre , )* K, V: Value, $( $post : $postcond ),* > $type <'a, $( $pre , )* K, V, $( $post ),* > {
             #[inline]
             pub fn iter_below_mut(&mut self) -> IterMut<K, V, impl TreeWriter<K, V> + 'a $( + $pre )*> {
                 let [front, back] = if let Some(current) = self.current {
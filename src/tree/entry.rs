@@ -0,0 +1,231 @@
+use std::mem::replace;
+
+use crate::{
+    arena::{Meta, MetaMut, PortAllocGuard},
+    tree::{
+        Tree, Bounds, Node, NodeIndex, Value, ValueMut, Color, SearchResult, Error, Comparator,
+        TreeAllocGuard, value_get_mut
+    }
+};
+/// View into a single entry of a [Tree], which is either vacant or occupied.
+///
+/// This is constructed using [TreeAllocGuard::entry].
+#[derive(Debug)]
+pub enum Entry<'a, 'b, K, V: Value> {
+    /// There is no node with the given key.
+    Vacant(VacantEntry<'a, 'b, K, V>),
+    /// There is a node with the given key.
+    Occupied(OccupiedEntry<'a, 'b, K, V>)
+}
+/// View into a vacant entry of a [Tree].
+/// This remembers the location of the search, so inserting does not need to search again.
+#[derive(Debug)]
+pub struct VacantEntry<'a, 'b, K, V: Value> {
+    pub(crate) tree: &'a mut PortAllocGuard<'b, Node<K, V>, Bounds>,
+    pub(crate) key: K,
+    pub(crate) parent: SearchResult<NodeIndex>
+}
+/// View into an occupied entry of a [Tree].
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, 'b, K, V: Value> {
+    tree: &'a mut PortAllocGuard<'b, Node<K, V>, Bounds>,
+    ptr: NodeIndex
+}
+
+impl<'a, K, V: Value, C> TreeAllocGuard<'a, K, V, C> {
+    /// Returns the entry of the given key for in-place manipulation.
+    #[inline]
+    pub fn entry(&mut self, key: K) -> Entry<'_, 'a, K, V>
+        where C: Comparator<K>
+    {
+        // SAFETY: root is a node in tree
+        match unsafe { Tree::search(self.0.meta().root, &key, self.1.comparator(), &self.0) } {
+            SearchResult::Here(ptr) => Entry::Occupied(OccupiedEntry { tree: &mut self.0, ptr }),
+            parent => Entry::Vacant(VacantEntry { tree: &mut self.0, key, parent })
+        }
+    }
+    /// Inserts the value at the given key, returns a mutable reference to the new value.
+    ///
+    /// When the key already exists, the tree is not changed and the key and value are returned with the error.
+    #[inline]
+    pub fn try_insert(&mut self, key: K, value: V::Local) -> Result<ValueMut<'_, K, V>, ((K, V::Local), Error)>
+        where C: Comparator<K>
+    {
+        // SAFETY: root is a node in tree
+        match unsafe { Tree::search(self.0.meta().root, &key, self.1.comparator(), &self.0) } {
+            SearchResult::Here(_) => Err(((key, value), Error::DuplicateKey)),
+            parent => Ok(VacantEntry { tree: &mut self.0, key, parent }.insert(value))
+        }
+    }
+}
+
+impl<'a, 'b, K, V: Value> Entry<'a, 'b, K, V> {
+    /// Returns the key of this entry.
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Self::Vacant(entry) => entry.key(),
+            Self::Occupied(entry) => entry.key()
+        }
+    }
+    /// Inserts the value if the entry is vacant, returns a mutable reference to the value.
+    #[inline]
+    pub fn or_insert(self, default: V::Local) -> ValueMut<'a, K, V> {
+        match self {
+            Self::Vacant(entry) => entry.insert(default),
+            Self::Occupied(entry) => entry.into_mut()
+        }
+    }
+    /// Inserts the result of the function if the entry is vacant, returns a mutable reference to the value.
+    #[inline]
+    pub fn or_insert_with<F>(self, default: F) -> ValueMut<'a, K, V>
+        where F: FnOnce() -> V::Local
+    {
+        match self {
+            Self::Vacant(entry) => entry.insert(default()),
+            Self::Occupied(entry) => entry.into_mut()
+        }
+    }
+    /// Inserts the result of the function if the entry is vacant, returns a mutable reference to the value.
+    ///
+    /// The function is given the key of the entry.
+    #[inline]
+    pub fn or_insert_with_key<F>(self, default: F) -> ValueMut<'a, K, V>
+        where F: FnOnce(&K) -> V::Local
+    {
+        match self {
+            Self::Vacant(entry) => {
+                let value = default(&entry.key);
+                entry.insert(value)
+            },
+            Self::Occupied(entry) => entry.into_mut()
+        }
+    }
+    /// Modifies the value of an occupied entry, this does nothing for vacant entries.
+    #[inline]
+    pub fn and_modify<F>(self, f: F) -> Self
+        where F: FnOnce(&mut V::Mut<'_>)
+    {
+        match self {
+            Self::Occupied(mut entry) => {
+                f(&mut entry.get_mut());
+                Self::Occupied(entry)
+            },
+            entry => entry
+        }
+    }
+}
+impl<'a, 'b, K, V: Value> Entry<'a, 'b, K, V>
+    where V::Local: Default
+{
+    /// Inserts the default value if the entry is vacant, returns a mutable reference to the value.
+    #[inline]
+    pub fn or_default(self) -> ValueMut<'a, K, V> {
+        self.or_insert_with(Default::default)
+    }
+}
+
+impl<'a, 'b, K, V: Value> VacantEntry<'a, 'b, K, V> {
+    /// Returns the key of this entry.
+    #[inline(always)]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    /// Returns ownership of the key.
+    #[inline(always)]
+    pub fn into_key(self) -> K {
+        self.key
+    }
+    /// Inserts the value into the tree, returns a mutable reference to the value.
+    #[inline]
+    pub fn insert(self, value: V::Local) -> ValueMut<'a, K, V> {
+        let tree: *mut PortAllocGuard<'b, Node<K, V>, Bounds> = self.tree;
+        let ptr = self.insert_node(value);
+        // SAFETY: tree is locked for the lifetime of the result
+        value_get_mut!((&mut *tree)[ptr], ptr, tree)
+    }
+    /// Links a new node at the location of the search, returns the pointer to the new node.
+    #[inline]
+    pub(crate) fn insert_node(self, value: V::Local) -> NodeIndex {
+        let Self { tree, key, parent } = self;
+        let value = V::new(value);
+        match parent {
+            SearchResult::LeftOf(parent) => {
+                let ptr = tree.insert(Node::new(key, value, Color::Red));
+                // SAFETY: parent is a leaf
+                unsafe { Tree::insert_at::<0>(ptr, parent, tree); }
+                ptr
+            },
+            SearchResult::RightOf(parent) => {
+                let ptr = tree.insert(Node::new(key, value, Color::Red));
+                // SAFETY: parent is a leaf
+                unsafe { Tree::insert_at::<1>(ptr, parent, tree); }
+                ptr
+            },
+            _ => {
+                let index = tree.insert(Node::new(key, value, Color::Black));
+                let ptr = Some(index);
+                let meta = tree.meta_mut();
+                meta.root = ptr;
+                meta.range = [ptr, ptr];
+                meta.black_height = 1;
+                meta.len = 1;
+                if V::has_cumulant() {
+                    tree[index].value.update_cumulant([None, None]);
+                }
+                index
+            }
+        }
+    }
+}
+
+impl<'a, 'b, K, V: Value> OccupiedEntry<'a, 'b, K, V> {
+    /// Returns the key of this entry.
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.tree[self.ptr].key
+    }
+    /// Returns a reference to the value of this entry.
+    #[inline]
+    pub fn get(&self) -> V::Ref<'_> {
+        self.tree[self.ptr].value.get()
+    }
+    /// Returns a mutable reference to the value of this entry.
+    #[inline]
+    pub fn get_mut(&mut self) -> ValueMut<'_, K, V> {
+        let tree: *mut PortAllocGuard<'b, Node<K, V>, Bounds> = self.tree;
+        // SAFETY: tree is locked for the lifetime of the result
+        value_get_mut!((&mut *tree)[self.ptr], self.ptr, tree)
+    }
+    /// Converts the entry into a mutable reference to its value.
+    #[inline]
+    pub fn into_mut(self) -> ValueMut<'a, K, V> {
+        let tree: *mut PortAllocGuard<'b, Node<K, V>, Bounds> = self.tree;
+        // SAFETY: tree is locked for the lifetime of the result
+        value_get_mut!((&mut *tree)[self.ptr], self.ptr, tree)
+    }
+    /// Replaces the value of this entry, returns the old value.
+    #[inline]
+    pub fn insert(&mut self, value: V::Local) -> V::Into {
+        let old = replace(&mut self.tree[self.ptr].value, V::new(value));
+        if V::has_cumulant() {
+            // SAFETY: node is the result of a search in tree
+            unsafe { Tree::propagate_cumulant(self.ptr, self.tree) };
+        }
+        old.into()
+    }
+    /// Removes this entry from the tree, returns the key and value.
+    #[inline]
+    pub fn remove_entry(self) -> (K, V::Into) {
+        // SAFETY: node is the result of a search in tree
+        let ptr = unsafe { Tree::remove_at(self.ptr, self.tree) };
+        // SAFETY: node was found, so it exists
+        let node = self.tree.remove(ptr).unwrap();
+        (node.key, node.value.into())
+    }
+    /// Removes this entry from the tree, returns the value.
+    #[inline(always)]
+    pub fn remove(self) -> V::Into {
+        self.remove_entry().1
+    }
+}
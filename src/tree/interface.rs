@@ -1,41 +1,185 @@
 use std::{
-    cmp::Ordering,
-    mem::take,
-    ops::RangeInclusive
+    borrow::Borrow,
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    mem::swap,
+    ops::{Bound, RangeBounds, RangeInclusive, Sub},
+    ptr::addr_of_mut
 };
 
 use crate::{
-    Writer, discard,
+    Writer,
     arena::{
         Meta, MetaMut,
         PortReadGuard, PortWriteGuard, PortAllocGuard
     },
     tree::{
         Error, Bounds, Tree, SearchResult,
+        Comparator, OrdComparator, range_contains, prefix_upper_bound, prefix_upper_bound_str,
         Node, NodeIndex, NodeRef,
-        Value, ValueMut, Color
+        Value, CombineCumulant, ValueMut, ValuesMut, Color, Entry
     }
 };
 
-impl<K: Ord, V: Value> Tree<K, V> {
+impl<K, V: Value, C> Tree<K, V, C> {
     /// Aquire read-only access.
     #[inline]
-    pub fn read(&self) -> TreeReadGuard<K, V> {
+    pub fn read(&self) -> TreeReadGuard<K, V, C> {
         TreeReadGuard(self.port.read(), self)
     }
     /// Aquire read-write access.
     #[inline]
-    pub fn write(&mut self) -> TreeWriteGuard<K, V> {
+    pub fn write(&mut self) -> TreeWriteGuard<K, V, C> {
         TreeWriteGuard(self.port.write(), self)
     }
     /// Aquire insert/remove access.
     #[inline]
-    pub fn alloc(&mut self) -> TreeAllocGuard<K, V> {
+    pub fn alloc(&mut self) -> TreeAllocGuard<K, V, C> {
         TreeAllocGuard(self.port.alloc(), self)
     }
-    /// Join two non-overlapping trees together.
+    #[inline]
+    pub(crate) fn split_at_root(mut self) -> (Self, NodeRef, Self) {
+        let mut write = self.write();
+        // SAFETY: the right sub-tree is moved into its own tree below
+        let (root, right_bounds) = unsafe { Tree::split_root(&mut write.0) };
+        drop(write);
+        let other = self.split_with_meta(right_bounds);
+        (self, root, other)
+    }
+    /// Split tree at a given in-order position, the node at this position is returned as the pivot.
+    #[inline]
+    pub(crate) fn split_nth(self, n: usize) -> (Self, NodeRef, Self) {
+        let left_len = {
+            let read = self.read();
+            if n >= read.len() {
+                drop(read);
+                let other = self.split_with_meta(Bounds::default());
+                return (self, None, other);
+            }
+            // SAFETY: tree is not empty
+            let root = &read.0[read.0.meta().root.unwrap()];
+            root.children[0].map_or(0, |child| read.0[child].size)
+        };
+        let (left, Some(root), right) = self.split_at_root()
+            else { unreachable!("tree is not empty") };
+        match n.cmp(&left_len) {
+            Ordering::Equal => (left, Some(root), right),
+            Ordering::Less => {
+                let (left, pivot, center) = left.split_nth(n);
+                // SAFETY: center and right are ordered by construction
+                let right = unsafe { Self::join_ordered(center, root, right) };
+                (left, pivot, right)
+            },
+            Ordering::Greater => {
+                let (center, pivot, right) = right.split_nth(n - left_len - 1);
+                // SAFETY: left and center are ordered by construction
+                let left = unsafe { Self::join_ordered(left, root, center) };
+                (left, pivot, right)
+            }
+        }
+    }
+    /// Split tree at a given in-order position, the left tree will contain the first `n` nodes.
+    ///
+    /// The position is found in `O(log(N))` using the sub-tree sizes.
+    #[inline]
+    pub fn split_at(self, n: usize) -> (Self, Self) {
+        let (left, pivot, mut right) = self.split_nth(n);
+        if let Some(pivot) = pivot {
+            // SAFETY: pivot was split off and is ordered before all nodes of right
+            unsafe { right.write().insert_node_at::<0>(pivot) };
+        }
+        (left, right)
+    }
+    /// Split tree into the nodes that match the predicate and the nodes that do not.
+    ///
+    /// The nodes are not reallocated, both trees are rebuilt in `O(N)`.
+    #[inline]
+    pub fn partition<F>(mut self, mut pred: F) -> (Self, Self)
+        where F: FnMut(&K, V::Ref<'_>) -> bool
+    {
+        let (matched, rest) = {
+            let read = self.read();
+            let mut matched = Vec::new();
+            let mut rest = Vec::with_capacity(read.len());
+            let mut ptr = read.0.meta().range[0];
+            while let Some(index) = ptr {
+                let node = &read.0[index];
+                ptr = node.order[1];
+                if pred(&node.key, node.value.get()) {
+                    matched.push(index);
+                } else {
+                    rest.push(index);
+                }
+            }
+            (matched, rest)
+        };
+        let mut other = self.split_with_meta(Bounds::default());
+        // SAFETY: nodes were collected in order and every node ends up in exactly one tree
+        unsafe {
+            Tree::relink_sorted_unchecked(&matched, &mut self.write().0);
+            Tree::relink_sorted_unchecked(&rest, &mut other.write().0);
+        }
+        (self, other)
+    }
+    /// Split tree at the first node where `pred` becomes true for the cumulant of all nodes up to and including that node,
+    /// the left tree will contain all nodes before it.
+    ///
+    /// `pred` has to be monotone, i.e. once it returns `true` for a prefix it does so for all longer prefixes.
+    /// Prefixes are combined using [CombineCumulant::local_cumulant] and [CombineCumulant::combine_cumulants].
+    ///
+    /// The position is found in `O(log(N))` using the cumulants stored in the tree.
+    pub fn split_by_cumulant<P>(self, mut pred: P) -> (Self, Self)
+        where V: CombineCumulant, P: FnMut(&V::Cumulant) -> bool
+    {
+        let mut n = 0;
+        {
+            let read = self.read();
+            // NOTE: `None` is the empty prefix
+            let mut prefix: Option<V::Cumulant> = None;
+            let mut ptr = read.0.meta().root;
+            while let Some(index) = ptr {
+                let node = &read.0[index];
+                let left = node.children[0].map( |left| &read.0[left] );
+                let joined;
+                let before = match (prefix.as_ref(), left) {
+                    (Some(prefix), Some(left)) => {
+                        joined = V::combine_cumulants(prefix, left.value.cumulant());
+                        Some(&joined)
+                    },
+                    (Some(prefix), None) => Some(prefix),
+                    (None, Some(left)) => Some(left.value.cumulant()),
+                    (None, None) => None
+                };
+                if let Some(left) = left {
+                    if before.is_some_and(&mut pred) {
+                        ptr = node.children[0];
+                        continue;
+                    }
+                    n += left.size;
+                }
+                let local = node.value.local_cumulant();
+                let cumulant = match before {
+                    Some(before) => V::combine_cumulants(before, &local),
+                    None => local
+                };
+                if pred(&cumulant) {
+                    break;
+                }
+                n += 1;
+                prefix = Some(cumulant);
+                ptr = node.children[1];
+            }
+        }
+        self.split_at(n)
+    }
+}
+impl<K, V: Value, C: Comparator<K>> Tree<K, V, C> {
+    /// Join two non-overlapping trees together, otherwise both trees are returned with the error.
     #[inline]
     pub fn union_disjoint(mut self, mut other: Self) -> Result<Self, ((Self, Self), Error)> {
+        if !self.port.shares_arena(&other.port) {
+            return Err(((self, other), Error::ForeignTree));
+        }
         {
             let this = self.read();
             match this.len_estimate() {
@@ -66,15 +210,16 @@ impl<K: Ord, V: Value> Tree<K, V> {
         }
         let mut this = self.write();
         let that = other.read();
+        let cmp = this.1.comparator();
         // SAFETY: this is not an empty tree
-        let pivot = if this.min() < that.min() {
-            if this.max() >= that.min() {
+        let pivot = if cmp.compare(this.min().unwrap(), that.min().unwrap()).is_lt() {
+            if cmp.compare(this.max().unwrap(), that.min().unwrap()).is_ge() {
                 drop((this, that));
                 return Err(((self, other), Error::Overlapping))
             }
             unsafe { this.0.meta().range[1].unwrap_unchecked() }
         } else {
-            if that.max() >= this.min() {
+            if cmp.compare(that.max().unwrap(), this.min().unwrap()).is_ge() {
                 drop((this, that));
                 return Err(((self, other), Error::Overlapping))
             }
@@ -86,11 +231,44 @@ impl<K: Ord, V: Value> Tree<K, V> {
         // SAFETY: checks were done before this
         Ok(unsafe { Self::join(self, pivot, other).unwrap_unchecked() })
     }
+    /// Join two trees together with a new node between them.
+    ///
+    /// This requires `self->max < key < other->min`, otherwise all inputs are returned with the error.
+    #[inline]
+    pub fn join_with(mut self, key: K, value: V::Local, other: Self) -> Result<Self, ((Self, (K, V::Local), Self), Error)> {
+        if !self.port.shares_arena(&other.port) {
+            return Err(((self, (key, value), other), Error::ForeignTree));
+        }
+        {
+            let this = self.read();
+            let that = other.read();
+            let cmp = this.1.comparator();
+            let ordered = this.max().map_or(true, |max| cmp.compare(max, &key).is_lt())
+                && that.min().map_or(true, |min| cmp.compare(&key, min).is_lt());
+            if !ordered {
+                drop((this, that));
+                return Err(((self, (key, value), other), Error::Overlapping));
+            }
+        }
+        let pivot = self.alloc().0.insert(Node::new(key, V::new(value), Color::Red));
+        // SAFETY: pivot is a new node and the order was checked before this
+        Ok(unsafe { Self::join_ordered(self, pivot, other) })
+    }
     /// Join two trees together.
-    /// Nodes that exists on both trees will be merged using a callback function.
+    /// Nodes that exists on both trees will be merged using a callback function,
+    /// which receives the value of this tree mutably and the value of the other tree by value.
     #[inline]
-    pub fn union_merge<F>(mut self, mut other: Self, merge: F) -> Self
-        where F: Fn(&mut V::Mut<'_>, V) + Clone
+    pub fn union_merge<F>(self, other: Self, mut merge: F) -> Result<Self, ((Self, Self), Error)>
+        where F: FnMut(&mut V::Mut<'_>, V)
+    {
+        if !self.port.shares_arena(&other.port) {
+            return Err(((self, other), Error::ForeignTree));
+        }
+        Ok(self.union_merge_by(other, &mut merge))
+    }
+    #[inline]
+    fn union_merge_by<F>(mut self, mut other: Self, merge: &mut F) -> Self
+        where F: FnMut(&mut V::Mut<'_>, V)
     {
         {
             let this = self.read();
@@ -103,9 +281,15 @@ impl<K: Ord, V: Value> Tree<K, V> {
                         Ok(()) => (),
                         Err(Error::DuplicateKey) => {
                             // SAFETY: root exists
-                            let root = alloc.0.remove(root).unwrap();
+                            let mut root = alloc.0.remove(root).unwrap();
                             // SAFETY: this error was causes because this exists
-                            let mut value = alloc.get_mut(&root.key).unwrap();
+                            let ptr = unsafe { Tree::search(alloc.0.meta().root, &root.key, alloc.1.comparator(), &alloc.0) }
+                                .into_here().unwrap();
+                            // NOTE: the value of this tree is the one merged into
+                            swap(&mut alloc.0[ptr].value, &mut root.value);
+                            let tree = addr_of_mut!(alloc.0);
+                            // SAFETY: tree is locked for the lifetime of value
+                            let mut value = value_get_mut!((&mut *tree)[ptr], ptr, tree);
                             merge(&mut value, root.value);
                         },
                         _ => panic!("unhandled error")
@@ -140,9 +324,8 @@ impl<K: Ord, V: Value> Tree<K, V> {
                 _ => ()
             }
         }
-        // SAFETY: other is not empty
         let (other_left, Some(other_root), other_right) = other.split_at_root()
-            else { panic!() };
+            else { unreachable!("other is not empty") };
         let (mut left, pivot, right) = {
             let read = other_left.read();
             let node = &read.0[other_root];
@@ -151,113 +334,262 @@ impl<K: Ord, V: Value> Tree<K, V> {
         if let Some(pivot) = pivot {
             let mut alloc = left.alloc();
             // SAFETY: pivot exists
-            let other = alloc.0.remove(pivot).unwrap();
+            let mut this = alloc.0.remove(pivot).unwrap();
             let node = &mut alloc.0[other_root];
+            // NOTE: the value of this tree is the one merged into
+            swap(&mut node.value, &mut this.value);
             // SAFETY: propagation is already done in the call to join later
-            merge(&mut unsafe { node.value.get_mut_unchecked() }, other.value);
+            merge(&mut unsafe { node.value.get_mut_unchecked() }, this.value);
         }
-        let left = left.union_merge(other_left, merge.clone());
-        let right = right.union_merge(other_right, merge);
+        let left = left.union_merge_by(other_left, merge);
+        let right = right.union_merge_by(other_right, merge);
         // SAFETY: left and right are disjoint by other_root by construction
         unsafe { Self::join(left, other_root, right).unwrap_unchecked() }
     }
+    /// Join many trees together, returns `None` when there are no trees.
+    /// Nodes that exists in multiple trees will be merged using a callback function,
+    /// the order in which the values of a key are merged is unspecified.
+    ///
+    /// The two smallest trees are always merged first, so joining `k` trees with `N` nodes in total
+    /// takes `O(N * log(k))` in the worst case and less when the trees are disjoint.
     #[inline]
-    pub(crate) fn split_at_root(mut self) -> (Self, NodeRef, Self) {
-        let mut write = self.write();
-        let Some(index) = write.0.meta().root
-            else {
-                drop(write);
-                let port = self.port.split_with_meta(Bounds::default());
-                return (self, None, Tree::new(port));
-            };
-        let node = &mut write.0[index];
-        let children = take(&mut node.children);
-        let order = take(&mut node.order);
-        let left_bounds = write.0.meta_mut();
-        let mut right_bounds = *left_bounds;
-        if let Some(index) = children[0] {
-            left_bounds.root = children[0];
-            left_bounds.range[1] = order[0];
-            discard! {
-                write.0[order[0]?].order[1] = None
-            };
-            let root = &mut write.0[index];
-            root.parent = None;
-            if root.is_red() {
-                root.color = Color::Black;
-            } else {
-                write.0.meta_mut().black_height -= 1;
-            }
-        } else {
-            *left_bounds = Bounds::default();
+    pub fn union_all<F>(trees: impl IntoIterator<Item = Self>, mut merge: F) -> Result<Option<Self>, (Vec<Self>, Error)>
+        where F: FnMut(&mut V::Mut<'_>, V)
+    {
+        let trees = trees.into_iter().collect::<Vec<_>>();
+        if trees.iter().any( |tree| !tree.port.shares_arena(&trees[0].port) ) {
+            return Err((trees, Error::ForeignTree));
         }
-        if let Some(index) = children[1] {
-            right_bounds.root = children[1];
-            right_bounds.range[0] = order[1];
-            discard! {
-                write.0[order[1]?].order[0] = None
-            };
-            let root = &mut write.0[index];
-            root.parent = None;
-            if root.is_red() {
-                root.color = Color::Black;
-            } else {
-                right_bounds.black_height -= 1;
+        let mut queue = trees.iter().enumerate()
+            .map( |(index, tree)| (Reverse(tree.read().len()), index) )
+            .collect::<BinaryHeap<_>>();
+        let mut trees = trees.into_iter().map(Some).collect::<Vec<_>>();
+        while let Some((_, a)) = queue.pop() {
+            let this = trees[a].take().expect("every tree is queued at most once");
+            let Some((_, b)) = queue.pop()
+                else { return Ok(Some(this)) };
+            let other = trees[b].take().expect("every tree is queued at most once");
+            let tree = this.union_merge_by(other, &mut merge);
+            queue.push((Reverse(tree.read().len()), a));
+            trees[a] = Some(tree);
+        }
+        Ok(None)
+    }
+    /// Join two trees together.
+    /// Nodes that exists on both trees will be merged using a callback function,
+    /// which receives the key and the value of this tree mutably and the value of the other tree by value,
+    /// like [`union_merge`](Self::union_merge), and decides whether the merged node is kept or both nodes are removed.
+    #[inline]
+    pub fn union_with<F>(self, other: Self, mut merge: F) -> Result<Self, ((Self, Self), Error)>
+        where F: FnMut(&K, V::Mut<'_>, V) -> MergeAction
+    {
+        if !self.port.shares_arena(&other.port) {
+            return Err(((self, other), Error::ForeignTree));
+        }
+        Ok(self.union_with_by(other, &mut merge))
+    }
+    #[inline]
+    fn union_with_by<F>(self, other: Self, merge: &mut F) -> Self
+        where F: FnMut(&K, V::Mut<'_>, V) -> MergeAction
+    {
+        if self.read().is_empty() {
+            return other;
+        }
+        if other.read().is_empty() {
+            return self;
+        }
+        let (other_left, Some(other_root), other_right) = other.split_at_root()
+            else { unreachable!("other is not empty") };
+        let (mut left, pivot, right) = {
+            let read = other_left.read();
+            let node = &read.0[other_root];
+            self.split_node(&node.key)
+        };
+        let action = pivot.map( |pivot| {
+            let mut alloc = left.alloc();
+            // SAFETY: other_root was split off, so it exists
+            let other = alloc.0.remove(other_root).unwrap();
+            let node = &mut alloc.0[pivot];
+            // SAFETY: propagation is already done in the call to join later
+            let action = merge(&node.key, unsafe { node.value.get_mut_unchecked() }, other.value);
+            if action == MergeAction::Remove {
+                alloc.0.remove(pivot);
             }
+            action
+        } );
+        let left = left.union_with_by(other_left, merge);
+        let right = right.union_with_by(other_right, merge);
+        match (pivot, action) {
+            // SAFETY: left and right are disjoint by other_root by construction
+            (None, _) => unsafe { Self::join(left, other_root, right).unwrap_unchecked() },
+            // SAFETY: left and right are disjoint by pivot by construction
+            (Some(pivot), Some(MergeAction::Keep)) => unsafe { Self::join(left, pivot, right).unwrap_unchecked() },
+            // SAFETY: left and right are disjoint by construction
+            _ => unsafe { Self::union_disjoint(left, right).unwrap_unchecked() }
+        }
+    }
+    /// Keep only the keys that exists on both trees, nodes of the other tree are freed.
+    /// The values of both trees will be merged using a callback function,
+    /// which receives the value of this tree mutably and the value of the other tree by value.
+    #[inline]
+    pub fn intersection<F>(self, other: Self, mut merge: F) -> Result<Self, ((Self, Self), Error)>
+        where F: FnMut(&mut V::Mut<'_>, V)
+    {
+        if !self.port.shares_arena(&other.port) {
+            return Err(((self, other), Error::ForeignTree));
+        }
+        Ok(self.intersection_by(other, &mut merge))
+    }
+    #[inline]
+    fn intersection_by<F>(mut self, mut other: Self, merge: &mut F) -> Self
+        where F: FnMut(&mut V::Mut<'_>, V)
+    {
+        if self.read().is_empty() {
+            other.alloc().clear();
+            return self;
+        }
+        if other.read().is_empty() {
+            self.alloc().clear();
+            return self;
+        }
+        let (other_left, Some(other_root), other_right) = other.split_at_root()
+            else { unreachable!("other is not empty") };
+        let (mut left, pivot, right) = {
+            let read = other_left.read();
+            let node = &read.0[other_root];
+            self.split_node(&node.key)
+        };
+        let mut alloc = left.alloc();
+        // SAFETY: other_root was split off, so it exists
+        let other = alloc.0.remove(other_root).unwrap();
+        if let Some(pivot) = pivot {
+            let node = &mut alloc.0[pivot];
+            // SAFETY: propagation is already done in the call to join later
+            merge(&mut unsafe { node.value.get_mut_unchecked() }, other.value);
+        }
+        drop(alloc);
+        let left = left.intersection_by(other_left, merge);
+        let right = right.intersection_by(other_right, merge);
+        match pivot {
+            // SAFETY: left and right are disjoint by pivot by construction
+            Some(pivot) => unsafe { Self::join(left, pivot, right).unwrap_unchecked() },
+            // SAFETY: left and right are disjoint by construction
+            None => unsafe { Self::union_disjoint(left, right).unwrap_unchecked() }
+        }
+    }
+    /// Remove all keys that exists in the other tree, nodes of the other tree are freed.
+    #[inline]
+    pub fn difference(self, other: Self) -> Result<Self, ((Self, Self), Error)> {
+        if !self.port.shares_arena(&other.port) {
+            return Err(((self, other), Error::ForeignTree));
+        }
+        Ok(self.difference_inner(other))
+    }
+    #[inline]
+    fn difference_inner(self, mut other: Self) -> Self {
+        if self.read().is_empty() || other.read().is_empty() {
+            other.alloc().clear();
+            return self;
+        }
+        let (other_left, Some(other_root), other_right) = other.split_at_root()
+            else { unreachable!("other is not empty") };
+        let (mut left, pivot, right) = {
+            let read = other_left.read();
+            let node = &read.0[other_root];
+            self.split_node(&node.key)
+        };
+        let mut alloc = left.alloc();
+        for ptr in [Some(other_root), pivot].into_iter().flatten() {
+            alloc.0.remove(ptr);
+        }
+        drop(alloc);
+        let left = left.difference_inner(other_left);
+        let right = right.difference_inner(other_right);
+        // SAFETY: left and right are disjoint by construction
+        unsafe { Self::union_disjoint(left, right).unwrap_unchecked() }
+    }
+    /// Keep only the keys that exists on exactly one of the trees, nodes that exist in both trees are freed.
+    #[inline]
+    pub fn symmetric_difference(self, other: Self) -> Result<Self, ((Self, Self), Error)> {
+        if !self.port.shares_arena(&other.port) {
+            return Err(((self, other), Error::ForeignTree));
+        }
+        Ok(self.symmetric_difference_inner(other))
+    }
+    #[inline]
+    fn symmetric_difference_inner(self, other: Self) -> Self {
+        if self.read().is_empty() {
+            return other;
+        }
+        if other.read().is_empty() {
+            return self;
+        }
+        let (other_left, Some(other_root), other_right) = other.split_at_root()
+            else { unreachable!("other is not empty") };
+        let (mut left, pivot, right) = {
+            let read = other_left.read();
+            let node = &read.0[other_root];
+            self.split_node(&node.key)
+        };
+        if let Some(pivot) = pivot {
+            let mut alloc = left.alloc();
+            alloc.0.remove(pivot);
+            alloc.0.remove(other_root);
+        }
+        let left = left.symmetric_difference_inner(other_left);
+        let right = right.symmetric_difference_inner(other_right);
+        if pivot.is_some() {
+            // SAFETY: left and right are disjoint by construction
+            unsafe { Self::union_disjoint(left, right).unwrap_unchecked() }
         } else {
-            right_bounds = Bounds::default();
+            // SAFETY: left and right are disjoint by other_root by construction
+            unsafe { Self::join(left, other_root, right).unwrap_unchecked() }
         }
-        drop(write);
-        let port = self.port.split_with_meta(right_bounds);
-        (self, Some(index), Tree::new(port))
     }
     #[inline]
-    pub(crate) fn split_node(mut self, key: &K) -> (Self, NodeRef, Self) {
+    pub(crate) fn split_node<Q>(mut self, key: &Q) -> (Self, NodeRef, Self)
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
         let mut write = self.write();
         if write.is_empty() {
             drop(write);
-            let port = self.port.split_with_meta(Bounds::default());
-            let other = Tree::new(port);
+            let other = self.split_with_meta(Bounds::default());
             return (self, None, other);
         }
         // SAFETY: tree is not empty
-        match write.min().unwrap().cmp(key) {
+        match write.1.comparator().compare(write.min().unwrap().borrow(), key) {
             Ordering::Greater => {
                 drop(write);
-                let port = self.port.split_with_meta(Bounds::default());
-                let other = Tree::new(port);
+                let other = self.split_with_meta(Bounds::default());
                 return (other, None, self);
             },
             Ordering::Equal => {
                 let min = write.0.meta().range[0].unwrap();
                 let min = unsafe { write.remove_node(min) };
                 drop(write);
-                let port = self.port.split_with_meta(Bounds::default());
-                let other = Tree::new(port);
+                let other = self.split_with_meta(Bounds::default());
                 return (other, Some(min), self);
             },
             _ => ()
         }
-        match write.max().unwrap().cmp(key) {
+        match write.1.comparator().compare(write.max().unwrap().borrow(), key) {
             Ordering::Less => {
                 drop(write);
-                let port = self.port.split_with_meta(Bounds::default());
-                let other = Tree::new(port);
+                let other = self.split_with_meta(Bounds::default());
                 return (self, None, other);
             },
             Ordering::Equal => {
                 let max = write.0.meta().range[1].unwrap();
                 let max = unsafe { write.remove_node(max) };
                 drop(write);
-                let port = self.port.split_with_meta(Bounds::default());
-                let other = Tree::new(port);
+                let other = self.split_with_meta(Bounds::default());
                 return (self, Some(max), other);
             },
             _ => ()
         }
         let index = write.0.meta().root.unwrap();
         let node = &mut write.0[index];
-        let root_cmp = node.key.cmp(key);
+        let root_cmp = write.1.comparator().compare(node.key.borrow(), key);
         match root_cmp {
             Ordering::Equal => {
                 drop(write);
@@ -266,8 +598,7 @@ impl<K: Ord, V: Value> Tree<K, V> {
             order => {
                 if write.is_single() {
                     drop(write);
-                    let port = self.port.split_with_meta(Bounds::default());
-                    let other = Tree::new(port);
+                    let other = self.split_with_meta(Bounds::default());
                     return if order.is_lt() {
                             (self, None, other)
                         } else {
@@ -275,9 +606,8 @@ impl<K: Ord, V: Value> Tree<K, V> {
                         };
                 }
                 drop(write);
-                // SAFETY: tree is not empty
                 let (left, Some(root), right) = self.split_at_root()
-                    else { panic!() };
+                    else { unreachable!("tree is not empty") };
                 if order.is_gt() {
                     let (left, left_child, center) = left.split_node(key);
                     // SAFETY: center and right are disjoint by construction
@@ -295,7 +625,9 @@ impl<K: Ord, V: Value> Tree<K, V> {
     }
     /// Split tree at a given key, also returns the value at the split location if it exists.
     #[inline]
-    pub fn split(self, key: &K) -> (Self, Option<V::Into>, Self) {
+    pub fn split<Q>(self, key: &Q) -> (Self, Option<V::Into>, Self)
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
         let (mut left, pivot, right) = self.split_node(key);
         let value = if let Some(index) = pivot {
             let mut alloc = left.alloc();
@@ -305,58 +637,172 @@ impl<K: Ord, V: Value> Tree<K, V> {
         } else { None };
         (left, value, right)
     }
+    /// Split tree at all given keys, the keys have to be sorted in strictly increasing order.
+    ///
+    /// Returns one more tree than there are keys, a key that exists in the tree will be the first node of the tree following it.
+    ///
+    /// # Panics
+    /// When the keys are not strictly increasing.
+    #[inline]
+    pub fn split_many<Q>(self, keys: &[Q]) -> Vec<Self>
+        where K: Borrow<Q>, C: Comparator<Q>
+    {
+        assert!(keys.windows(2).all( |keys| self.cmp.compare(&keys[0], &keys[1]).is_lt() ),
+            "keys have to be strictly increasing");
+        let mut trees = Vec::with_capacity(keys.len() + 1);
+        self.split_many_into(keys, &mut trees);
+        trees
+    }
+    fn split_many_into<Q>(self, keys: &[Q], trees: &mut Vec<Self>)
+        where K: Borrow<Q>, C: Comparator<Q>
+    {
+        if keys.is_empty() {
+            trees.push(self);
+            return;
+        }
+        let mid = keys.len() >> 1;
+        let (left, pivot, mut right) = self.split_node(&keys[mid]);
+        if let Some(pivot) = pivot {
+            // SAFETY: pivot was split off and is ordered before all nodes of right
+            unsafe { right.write().insert_node_at::<0>(pivot) };
+        }
+        left.split_many_into(&keys[..mid], trees);
+        right.split_many_into(&keys[mid + 1..], trees);
+    }
+    /// Join ordered and non-overlapping trees together, this is the inverse of [split_many](Tree::split_many).
+    /// Returns `None` when there are no trees.
+    #[inline]
+    pub fn concat(mut trees: Vec<Self>) -> Result<Option<Self>, (Vec<Self>, Error)> {
+        if trees.is_empty() {
+            return Ok(None);
+        }
+        let error = {
+            let reads = trees.iter().map(Self::read).collect::<Vec<_>>();
+            let cmp = reads[0].1.comparator();
+            let mut max = None;
+            let mut error = None;
+            for read in reads.iter() {
+                if !read.1.port.shares_arena(&reads[0].1.port) {
+                    error = Some(Error::ForeignTree);
+                    break;
+                }
+                let Some(min) = read.min()
+                    else { continue };
+                if max.is_some_and( |max| cmp.compare(max, min).is_ge() ) {
+                    error = Some(Error::Overlapping);
+                    break;
+                }
+                max = read.max();
+            }
+            error
+        };
+        if let Some(error) = error {
+            return Err((trees, error));
+        }
+        Ok(Some(Self::concat_unchecked(&mut trees)))
+    }
+    fn concat_unchecked(trees: &mut Vec<Self>) -> Self {
+        if trees.len() == 1 {
+            // SAFETY: length was checked before
+            return unsafe { trees.pop().unwrap_unchecked() };
+        }
+        let mut right = trees.split_off(trees.len() >> 1);
+        let left = Self::concat_unchecked(trees);
+        let mut right = Self::concat_unchecked(&mut right);
+        let mut write = right.write();
+        let Some(min) = write.0.meta().range[0]
+            else {
+                drop(write);
+                return left;
+            };
+        // SAFETY: min was retrived from this tree
+        let pivot = unsafe { write.remove_node(min) };
+        drop(write);
+        // SAFETY: all trees are ordered, this was checked before
+        unsafe { Self::join_ordered(left, pivot, right) }
+    }
+    /// Cut all keys starting with the prefix out of the tree.
+    /// Returns the remaining tree and the removed keys as their own tree.
+    #[inline]
+    pub fn split_prefix(self, prefix: &[u8]) -> (Self, Self)
+        where K: Borrow<[u8]>, C: Comparator<[u8]>
+    {
+        let upper = prefix_upper_bound(prefix);
+        self.split_range((Bound::Included(prefix), upper.as_deref().map_or(Bound::Unbounded, Bound::Excluded)))
+    }
+    /// Cut all keys starting with the prefix out of the tree.
+    /// Returns the remaining tree and the removed keys as their own tree.
+    #[inline]
+    pub fn split_prefix_str(self, prefix: &str) -> (Self, Self)
+        where K: Borrow<str>, C: Comparator<str>
+    {
+        let upper = prefix_upper_bound_str(prefix);
+        self.split_range((Bound::Included(prefix), upper.as_deref().map_or(Bound::Unbounded, Bound::Excluded)))
+    }
+    /// Cut all keys inside of the range out of the tree.
+    /// Returns the remaining tree and the removed range as its own tree.
+    #[inline]
+    pub fn split_range<Q, R>(self, range: R) -> (Self, Self)
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, R: RangeBounds<Q>
+    {
+        let (left, lower, rest) = match range.start_bound() {
+            Bound::Included(key) | Bound::Excluded(key) => self.split_node(key),
+            Bound::Unbounded => {
+                let other = self.split_with_meta(Bounds::default());
+                (other, None, self)
+            }
+        };
+        let (mut inner, upper, right) = match range.end_bound() {
+            Bound::Included(key) | Bound::Excluded(key) => rest.split_node(key),
+            Bound::Unbounded => {
+                let other = rest.split_with_meta(Bounds::default());
+                (rest, None, other)
+            }
+        };
+        // SAFETY: left and right are separated by the first split
+        let mut outer = unsafe { Self::union_disjoint(left, right).unwrap_unchecked() };
+        for pivot in [lower, upper].into_iter().flatten() {
+            let contained = range_contains(&range, inner.read().0[pivot].key.borrow(), inner.comparator());
+            let mut write = if contained { inner.write() } else { outer.write() };
+            // SAFETY: pivot was split off and is not part of any tree
+            unsafe { write.insert_node(pivot).unwrap_unchecked() };
+        }
+        (outer, inner)
+    }
 }
 
 #[derive(Debug)]
-pub struct TreeReadGuard<'a, K: Ord, V: Value>(pub(crate) PortReadGuard<'a, Node<K, V>, Bounds>, &'a Tree<K, V>);
+pub struct TreeReadGuard<'a, K, V: Value, C = OrdComparator>(pub(crate) PortReadGuard<'a, Node<K, V>, Bounds>, pub(crate) &'a Tree<K, V, C>);
 
 #[derive(Debug)]
-pub struct TreeWriteGuard<'a, K: Ord, V: Value>(pub(crate) PortWriteGuard<'a, Node<K, V>, Bounds>, &'a Tree<K, V>);
+pub struct TreeWriteGuard<'a, K, V: Value, C = OrdComparator>(pub(crate) PortWriteGuard<'a, Node<K, V>, Bounds>, pub(crate) &'a Tree<K, V, C>);
 
 #[derive(Debug)]
-pub struct TreeAllocGuard<'a, K: Ord, V: Value>(pub(crate) PortAllocGuard<'a, Node<K, V>, Bounds>, &'a Tree<K, V>);
-impl<'a, K: Ord, V: Value> TreeAllocGuard<'a, K, V> {
+pub struct TreeAllocGuard<'a, K, V: Value, C = OrdComparator>(pub(crate) PortAllocGuard<'a, Node<K, V>, Bounds>, pub(crate) &'a Tree<K, V, C>);
+impl<'a, K, V: Value, C> TreeAllocGuard<'a, K, V, C> {
     #[inline]
-    pub fn downgrade(self) -> TreeWriteGuard<'a, K, V> {
+    pub fn downgrade(self) -> TreeWriteGuard<'a, K, V, C> {
         TreeWriteGuard(self.0.downgrade(), self.1)
     }
+    /// Inserts the value at the given key, returns the previous value if the key already existed.
     #[inline]
-    pub fn insert(&mut self, key: K, value: V::Local) -> bool {
-        let value = V::new(value);
-        // SAFETY: root is a node in tree
-        match unsafe { Tree::search(self.0.meta().root, &key, &self.0) } {
-            SearchResult::Here(ptr) => {
-                self.0[ptr].value = value;
-                return false;
-            },
-            SearchResult::Empty => {
-                let index = self.0.insert(Node::new(key, value, Color::Black));
-                let ptr = Some(index);
-                let meta = self.0.meta_mut();
-                meta.root = ptr;
-                meta.range = [ptr, ptr];
-                meta.black_height = 1;
-                if V::has_cumulant() {
-                    self.0[index].value.update_cumulant([None, None]);
-                }
-            },
-            SearchResult::LeftOf(parent) => {
-                let ptr = self.0.insert(Node::new(key, value, Color::Red));
-                // SAFETY: parent is a leaf
-                unsafe { Tree::insert_at::<0>(ptr, parent, &mut self.0); }
-            },
-            SearchResult::RightOf(parent) => {
-                let ptr = self.0.insert(Node::new(key, value, Color::Red));
-                // SAFETY: parent is a leaf
-                unsafe { Tree::insert_at::<1>(ptr, parent, &mut self.0); }
+    pub fn insert(&mut self, key: K, value: V::Local) -> Option<V::Into>
+        where C: Comparator<K>
+    {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert_node(value);
+                None
             }
         }
-        true
     }
     #[inline]
-    pub fn remove(&mut self, key: K) -> Option<V::Into> {
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V::Into>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
         // SAFETY: root is a node in tree
-        match unsafe { Tree::search(self.0.meta().root, &key, &self.0) } {
+        match unsafe { Tree::search(self.0.meta().root, key, self.1.comparator(), &self.0) } {
             SearchResult::Here(ptr) => {
                 // SAFETY: node is the result of a search in tree
                 let ptr = unsafe { Tree::remove_at(ptr, &mut self.0) };
@@ -366,6 +812,126 @@ impl<'a, K: Ord, V: Value> TreeAllocGuard<'a, K, V> {
             _ => None
         }
     }
+    /// Changes the key of an existing node, the node keeps its value and its location in memory.
+    ///
+    /// When the new key already exists or the old key does not exist, the tree is not changed and the new key is returned with the error.
+    #[inline]
+    pub fn rekey<Q>(&mut self, old: &Q, new: K) -> Result<(), (K, Error)>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q> + Comparator<K>
+    {
+        let cmp = self.1.comparator();
+        let root = self.0.meta().root;
+        // SAFETY: root is a node in tree
+        let Some(ptr) = unsafe { Tree::search(root, old, cmp, &self.0) }.into_here()
+            else { return Err((new, Error::MissingKey)) };
+        // SAFETY: root is a node in tree
+        if let SearchResult::Here(other) = unsafe { Tree::search(root, &new, cmp, &self.0) } {
+            if other != ptr {
+                return Err((new, Error::DuplicateKey));
+            }
+        }
+        let [prev, next] = self.0[ptr].order;
+        let after_prev = !prev.is_some_and( |prev| Comparator::<K>::compare(cmp, &self.0[prev].key, &new).is_ge() );
+        let before_next = !next.is_some_and( |next| Comparator::<K>::compare(cmp, &new, &self.0[next].key).is_ge() );
+        if after_prev && before_next {
+            // NOTE: the node keeps its neighbours, so the order is still valid
+            self.0[ptr].key = new;
+            return Ok(());
+        }
+        // SAFETY: node is part of tree
+        let free = unsafe { Tree::remove_at(ptr, &mut self.0) };
+        if free != ptr {
+            // SAFETY: remove_at moved the successor into this node, free is not linked into the tree anymore
+            unsafe { Tree::relocate(ptr, free, &mut self.0) };
+        }
+        self.0[ptr].key = new;
+        // SAFETY: node was unlinked before and the new key does not exist
+        unsafe { self.insert_node(ptr).unwrap_unchecked() };
+        Ok(())
+    }
+    /// Removes the lowest key, returns the key and value.
+    #[inline]
+    pub fn pop_first(&mut self) -> Option<(K, V::Into)> {
+        let ptr = self.0.meta().range[0]?;
+        // SAFETY: node is part of tree
+        let ptr = unsafe { Tree::remove_at(ptr, &mut self.0) };
+        // SAFETY: node was unlinked, so it exists
+        let node = self.0.remove(ptr).unwrap();
+        Some((node.key, node.value.into()))
+    }
+    /// Removes the biggest key, returns the key and value.
+    #[inline]
+    pub fn pop_last(&mut self) -> Option<(K, V::Into)> {
+        let ptr = self.0.meta().range[1]?;
+        // SAFETY: node is part of tree
+        let ptr = unsafe { Tree::remove_at(ptr, &mut self.0) };
+        // SAFETY: node was unlinked, so it exists
+        let node = self.0.remove(ptr).unwrap();
+        Some((node.key, node.value.into()))
+    }
+    /// Removes all nodes inside of the range, returns the number of removed nodes.
+    ///
+    /// The range is cut out using split/join in `O(log(N))`, freeing the `M` removed nodes takes another `O(M)`.
+    #[inline]
+    pub fn remove_range<Q, R>(&mut self, range: R) -> usize
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, R: RangeBounds<Q>
+    {
+        let root = self.0.meta().root;
+        // SAFETY: root is a node in tree
+        let range = unsafe { Tree::rank_range(root, &range, self.1.comparator(), &self.0) };
+        if range.is_empty() {
+            return 0;
+        }
+        // SAFETY: the nodes after the range are appended again and the nodes inside of it are freed
+        unsafe {
+            let right = Tree::split_off(range.end, &mut self.0);
+            let inner = Tree::split_off(range.start, &mut self.0);
+            Tree::append(right, &mut self.0);
+            Tree::free_detached(inner, &mut self.0);
+        }
+        range.len()
+    }
+    /// Removes all nodes for which the function returns `false`.
+    ///
+    /// The tree will be rebuilt in `O(N)` when too many nodes are removed to do it one by one.
+    #[inline]
+    pub fn retain<F>(&mut self, mut keep: F)
+        where F: FnMut(&K, V::Mut<'_>) -> bool
+    {
+        let mut kept = Vec::with_capacity(self.len());
+        let mut removed = Vec::new();
+        let mut ptr = self.0.meta().range[0];
+        while let Some(index) = ptr {
+            let node = &mut self.0[index];
+            ptr = node.order[1];
+            // SAFETY: cumulants will be updated afterwards
+            if keep(&node.key, unsafe { node.value.get_mut_unchecked() }) {
+                kept.push(index);
+            } else {
+                removed.push(index);
+            }
+        }
+        let black_height = self.0.meta().black_height as usize;
+        if removed.len() * black_height < kept.len() {
+            // NOTE: removing in reverse order ensures that nodes moved by remove_at were not marked
+            for index in removed.into_iter().rev() {
+                // SAFETY: node is part of tree
+                let ptr = unsafe { Tree::remove_at(index, &mut self.0) };
+                self.0.remove(ptr);
+            }
+            if V::has_cumulant() {
+                let Some(root) = self.0.meta().root else { return };
+                // SAFETY: root exists and has no ancestors
+                unsafe { Tree::update_cumulants(root, &mut self.0) };
+            }
+        } else {
+            for index in removed {
+                self.0.remove(index);
+            }
+            // SAFETY: nodes were collected in order
+            unsafe { Tree::relink_sorted_unchecked(&kept, &mut self.0) };
+        }
+    }
     #[inline]
     pub fn clear(&mut self) {
         let mut ptr = self.0.meta().range[0];
@@ -373,36 +939,191 @@ impl<'a, K: Ord, V: Value> TreeAllocGuard<'a, K, V> {
             ptr = self.0[index].order[1];
             self.0.remove(index);
         }
-        let meta = self.0.meta_mut();
-        meta.root = None;
-        meta.range = [None, None];
+        *self.0.meta_mut() = Bounds::default();
     }
 }
 
 macro_rules! value_get_mut {
     ( $node:expr , $ptr:expr , $tree:expr ) => {
-        ValueMut(unsafe { $node .value.get_mut_unchecked() }, $ptr , $tree )
+        unsafe { ValueMut::new($node .value.get_mut_unchecked(), $ptr , $tree ) }
     };
 }
+pub(crate) use value_get_mut;
 
 macro_rules! impl_Reader {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns a reference to the value of the given node.
             #[inline]
-            pub fn get(&self, key: &K) -> Option<V::Ref<'_>> {
+            pub fn get<Q>(&self, key: &Q) -> Option<V::Ref<'_>>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
                 // SAFETY: root is a node in tree
-                let ptr = unsafe { Tree::search(self.0.meta().root, key, &self.0) }
+                let ptr = unsafe { Tree::search(self.0.meta().root, key, self.1.comparator(), &self.0) }
                     .into_here()?;
                 Some(self.0[ptr].value.get())
             }
             /// Returns `true` when the key exists.
             #[inline]
-            pub fn contains(&self, key: &K) -> bool {
+            pub fn contains<Q>(&self, key: &Q) -> bool
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
                 // SAFETY: root is a node in tree
-                unsafe { Tree::search(self.0.meta().root, key, &self.0) }
+                unsafe { Tree::search(self.0.meta().root, key, self.1.comparator(), &self.0) }
                     .is_here()
             }
+            /// Returns the in-order position of the given key.
+            ///
+            /// When the key does not exist, this returns the position it would be inserted at as an error,
+            /// similar to the standard libary binary search for slices.
+            #[inline]
+            pub fn rank<Q>(&self, key: &Q) -> Result<usize, usize>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                // SAFETY: root is a node in tree
+                unsafe { Tree::rank(self.0.meta().root, key, self.1.comparator(), &self.0) }
+            }
+            /// Returns the number of nodes inside of the range.
+            ///
+            /// This runs in `O(log(N))` using the sub-tree sizes.
+            #[inline]
+            pub fn count_range<Q, R>(&self, range: R) -> usize
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, R: RangeBounds<Q>
+            {
+                // SAFETY: root is a node in tree
+                unsafe { Tree::rank_range(self.0.meta().root, &range, self.1.comparator(), &self.0) }
+                    .len()
+            }
+            /// Returns the key and value at the given in-order position.
+            #[inline]
+            pub fn select(&self, index: usize) -> Option<(&K, V::Ref<'_>)> {
+                // SAFETY: root is a node in tree
+                let ptr = unsafe { Tree::select(self.0.meta().root, index, &self.0) }?;
+                let node = &self.0[ptr];
+                Some((&node.key, node.value.get()))
+            }
+            /// Returns the key at the given in-order position.
+            #[inline]
+            pub fn key_at(&self, index: usize) -> Option<&K> {
+                // SAFETY: root is a node in tree
+                let ptr = unsafe { Tree::select(self.0.meta().root, index, &self.0) }?;
+                Some(&self.0[ptr].key)
+            }
+            /// Returns the key and value of the biggest key less than or equal to the given key.
+            #[inline]
+            pub fn floor<Q>(&self, key: &Q) -> Option<(&K, V::Ref<'_>)>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                self.upper_bound(Bound::Included(key))
+            }
+            /// Returns the key and value of the lowest key greater than or equal to the given key.
+            #[inline]
+            pub fn ceiling<Q>(&self, key: &Q) -> Option<(&K, V::Ref<'_>)>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                self.lower_bound(Bound::Included(key))
+            }
+            /// Returns the key and value of the lowest key above the bound.
+            ///
+            /// `Bound::Excluded` gives the strict successor of a key, `Bound::Unbounded` gives the lowest key.
+            #[inline]
+            pub fn lower_bound<Q>(&self, bound: Bound<&Q>) -> Option<(&K, V::Ref<'_>)>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let ptr = Tree::closest_bound::<1, _, _>(bound, self.1.comparator(), &self.0)?;
+                let node = &self.0[ptr];
+                Some((&node.key, node.value.get()))
+            }
+            /// Returns the key and value of the biggest key below the bound.
+            ///
+            /// `Bound::Excluded` gives the strict predecessor of a key, `Bound::Unbounded` gives the biggest key.
+            #[inline]
+            pub fn upper_bound<Q>(&self, bound: Bound<&Q>) -> Option<(&K, V::Ref<'_>)>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let ptr = Tree::closest_bound::<0, _, _>(bound, self.1.comparator(), &self.0)?;
+                let node = &self.0[ptr];
+                Some((&node.key, node.value.get()))
+            }
+            /// Returns the key and value of the node with the smallest distance to the given key.
+            ///
+            /// The distance is calculated as `dist(key, other)`, ties are resolved in favour of the lower key.
+            #[inline]
+            pub fn nearest<Q, D, F>(&self, key: &Q, dist: F) -> Option<(&K, V::Ref<'_>)>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, D: PartialOrd, F: Fn(&Q, &Q) -> D
+            {
+                let floor = Tree::closest_bound::<0, _, _>(Bound::Included(key), self.1.comparator(), &self.0);
+                let ceiling = match floor {
+                    Some(ptr) => self.0[ptr].order[1],
+                    None => self.0.meta().range[0]
+                };
+                let ptr = match (floor, ceiling) {
+                    (Some(lower), Some(upper)) =>
+                        if dist(key, self.0[upper].key.borrow()) < dist(key, self.0[lower].key.borrow()) { upper }
+                        else { lower },
+                    (lower, upper) => lower.or(upper)?
+                };
+                let node = &self.0[ptr];
+                Some((&node.key, node.value.get()))
+            }
+            /// Returns the key and value of the node closest to the given key,
+            /// using the absolute difference as distance.
+            #[inline]
+            pub fn nearest_abs<Q>(&self, key: &Q) -> Option<(&K, V::Ref<'_>)>
+                where K: Borrow<Q>, C: Comparator<Q>, Q: Sub<Output = Q> + PartialOrd + Copy
+            {
+                self.nearest(key, |a, b| if a < b { *b - *a } else { *a - *b })
+            }
+            /// Returns the keys and values of up to `k` nodes with the smallest distance to the given key,
+            /// sorted by increasing distance.
+            ///
+            /// The distance is calculated as `dist(key, other)`, ties are resolved in favour of the lower key.
+            /// This walks outwards from the position of the key, so the distance has to grow with the in-order distance.
+            #[inline]
+            pub fn k_nearest<Q, D, F>(&self, key: &Q, k: usize, dist: F) -> Vec<(&K, V::Ref<'_>)>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, D: PartialOrd, F: Fn(&Q, &Q) -> D
+            {
+                let mut below = Tree::closest_bound::<0, _, _>(Bound::Included(key), self.1.comparator(), &self.0);
+                let mut above = match below {
+                    Some(ptr) => self.0[ptr].order[1],
+                    None => self.0.meta().range[0]
+                };
+                let mut result = Vec::with_capacity(k.min(self.0.meta().len));
+                while result.len() < k {
+                    let ptr = match (below, above) {
+                        (Some(lower), Some(upper)) if dist(key, self.0[upper].key.borrow()) < dist(key, self.0[lower].key.borrow()) => {
+                            above = self.0[upper].order[1];
+                            upper
+                        },
+                        (Some(lower), _) => {
+                            below = self.0[lower].order[0];
+                            lower
+                        },
+                        (None, Some(upper)) => {
+                            above = self.0[upper].order[1];
+                            upper
+                        },
+                        (None, None) => break
+                    };
+                    let node = &self.0[ptr];
+                    result.push((&node.key, node.value.get()));
+                }
+                result
+            }
+            /// Returns the key and value of the lowest key.
+            #[inline]
+            pub fn first_key_value(&self) -> Option<(&K, V::Ref<'_>)> {
+                let ptr = self.0.meta().range[0]?;
+                let node = &self.0[ptr];
+                Some((&node.key, node.value.get()))
+            }
+            /// Returns the key and value of the biggest key.
+            #[inline]
+            pub fn last_key_value(&self) -> Option<(&K, V::Ref<'_>)> {
+                let ptr = self.0.meta().range[1]?;
+                let node = &self.0[ptr];
+                Some((&node.key, node.value.get()))
+            }
         }
     };
 }
@@ -412,61 +1133,110 @@ impl_Reader!(TreeAllocGuard);
 
 macro_rules! impl_Writer {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns a mutable reference to the value of the given node.
             #[inline]
-            pub fn get_mut(&mut self, key: &K) -> Option<ValueMut<K, V>> {
+            pub fn get_mut<Q>(&mut self, key: &Q) -> Option<ValueMut<K, V>>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
                 // SAFETY: root is a node in tree
-                let ptr = unsafe { Tree::search(self.0.meta().root, key, &self.0) }
+                let ptr = unsafe { Tree::search(self.0.meta().root, key, self.1.comparator(), &self.0) }
                     .into_here()?;
-                Some(value_get_mut!(self.0[ptr], ptr, self.1))
+                let tree = addr_of_mut!(self.0);
+                // SAFETY: tree is locked for the lifetime of the result
+                Some(value_get_mut!((&mut *tree)[ptr], ptr, tree))
+            }
+            /// Returns a mutable reference to the value of the lowest key.
+            #[inline]
+            pub fn first_mut(&mut self) -> Option<ValueMut<K, V>> {
+                let ptr = self.0.meta().range[0]?;
+                let tree = addr_of_mut!(self.0);
+                // SAFETY: tree is locked for the lifetime of the result
+                Some(value_get_mut!((&mut *tree)[ptr], ptr, tree))
+            }
+            /// Returns a mutable reference to the value of the biggest key.
+            #[inline]
+            pub fn last_mut(&mut self) -> Option<ValueMut<K, V>> {
+                let ptr = self.0.meta().range[1]?;
+                let tree = addr_of_mut!(self.0);
+                // SAFETY: tree is locked for the lifetime of the result
+                Some(value_get_mut!((&mut *tree)[ptr], ptr, tree))
             }
             /// Returns mutable references to two destinct nodes.
+            ///
+            /// The cumulants of both nodes are propagated when the returned object is dropped.
             #[inline]
-            pub fn get_pair_mut(&mut self, a: &K, b: &K) -> Result<[Option<ValueMut<K, V>>; 2], Error> {
-                if a == b {
+            pub fn get_pair_mut<Q>(&mut self, a: &Q, b: &Q) -> Result<ValuesMut<K, V, 2>, Error>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                if self.1.comparator().compare(a, b).is_eq() {
                     return Err(Error::KeyAlias);
                 }
                 let root = self.0.meta().root;
                 // SAFETY: root is a node in tree
-                let a = unsafe { Tree::search(root, a, &self.0) };
-                let b = unsafe { Tree::search(root, b, &self.0) };
-                match (a, b) {
-                    (SearchResult::Here(a), SearchResult::Here(b)) => {
-                        // SAFETY: a and b are checked before this
-                        let [node_a, node_b] = self.0.get_pair_mut(a, b).unwrap();
-                        Ok([
-                            node_a.map( |node_a| value_get_mut!(node_a, a, self.1) ),
-                            node_b.map( |node_b| value_get_mut!(node_b, b, self.1) )
-                        ])
-                    },
-                    (SearchResult::Here(a), _) => Ok([Some(value_get_mut!(self.0[a], a, self.1)), None]),
-                    (_, SearchResult::Here(b)) => Ok([None, Some(value_get_mut!(self.0[b], b, self.1))]),
-                    _ => Ok([None, None])
-                }
+                let ptrs = [a, b].map( |key| unsafe { Tree::search(root, key, self.1.comparator(), &self.0) }.into_here() );
+                let tree = addr_of_mut!(self.0);
+                // SAFETY: a and b are checked before this
+                let [node_a, node_b] = unsafe { (&mut *tree).get_many_mut(ptrs) }.unwrap();
+                let nodes = [
+                    ptrs[0].zip(node_a.map( |node| node as *mut _ )),
+                    ptrs[1].zip(node_b.map( |node| node as *mut _ ))
+                ];
+                // SAFETY: tree is locked for the lifetime of the result
+                Ok(unsafe { ValuesMut::new(nodes, [], tree) })
             }
             /// Returns a mutable reference to a node and read-only references to multiple other nodes.
+            ///
+            /// The cumulants of the mutable node are propagated when the returned object is dropped.
             #[inline]
-            pub fn get_mut_with<const N: usize>(&mut self, key: &K, others: [Option<&K>; N]) -> Result<(Option<ValueMut<K, V>>, [Option<V::Ref<'_>>; N]), Error> {
-                if others.iter().any( |k| k.is_some_and( |k| k == key ) ) {
+            pub fn get_mut_with<const N: usize, Q>(&mut self, key: &Q, others: [Option<&Q>; N]) -> Result<ValuesMut<K, V, 1, N>, Error>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                if others.iter().any( |k| k.is_some_and( |k| self.1.comparator().compare(k, key).is_eq() ) ) {
                     return Err(Error::KeyAlias)
                 }
                 let root = self.0.meta().root;
                 // SAFETY: root is a node in tree
-                if let SearchResult::Here(x) = unsafe { Tree::search(root, key, &self.0) } {
-                    let others = others.map( |k| k.and_then( |k|
-                        unsafe { Tree::search(root, k, &self.0) }
-                            .into_here()
-                    ) );
-                    // SAFETY: all keys are checked before this
-                    let (x_node, others) = self.0.get_mut_with(x, others).unwrap();
-                    Ok((
-                        x_node.map( |x_node| value_get_mut!(x_node, x, self.1) ),
-                        others.map( |x| x.map( |x| x.value.get() ) )
-                    ))
-                } else {
-                    Ok((None, others.map( |k| k.and_then( |k| self.get(k) ) )))
+                let x = unsafe { Tree::search(root, key, self.1.comparator(), &self.0) }.into_here();
+                let others = others.map( |k| k.and_then( |k|
+                    unsafe { Tree::search(root, k, self.1.comparator(), &self.0) }
+                        .into_here()
+                ) );
+                let tree = addr_of_mut!(self.0);
+                let (node, others) = match x {
+                    Some(x) => {
+                        // SAFETY: all keys are checked before this
+                        let (node, others) = unsafe { (&mut *tree).get_mut_with(x, others) }.unwrap();
+                        (node.map( |node| (x, node as *mut _) ), others.map( |node| node.map( |node| node as *const _ ) ))
+                    },
+                    // SAFETY: all nodes were found in this tree
+                    None => (None, others.map( |x| x.map( |x| unsafe { &(&*tree)[x] } as *const _ ) ))
+                };
+                // SAFETY: tree is locked for the lifetime of the result
+                Ok(unsafe { ValuesMut::new([node], others, tree) })
+            }
+            /// Returns mutable references to multiple destinct nodes.
+            ///
+            /// The cumulants of all nodes are propagated when the returned object is dropped.
+            #[inline]
+            pub fn get_many_mut<const N: usize, Q>(&mut self, keys: [&Q; N]) -> Result<ValuesMut<K, V, N>, Error>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let cmp = self.1.comparator();
+                for (n, key) in keys.iter().enumerate() {
+                    if keys[..n].iter().any( |k| cmp.compare(k, key).is_eq() ) {
+                        return Err(Error::KeyAlias);
+                    }
                 }
+                let root = self.0.meta().root;
+                // SAFETY: root is a node in tree
+                let ptrs = keys.map( |key| unsafe { Tree::search(root, key, cmp, &self.0) }.into_here() );
+                let tree = addr_of_mut!(self.0);
+                // SAFETY: keys are checked before this
+                let mut nodes = unsafe { (&mut *tree).get_many_mut(ptrs) }.unwrap().into_iter();
+                let nodes = ptrs.map( |ptr| ptr.zip(nodes.next().unwrap().map( |node| node as *mut _ )) );
+                // SAFETY: tree is locked for the lifetime of the result
+                Ok(unsafe { ValuesMut::new(nodes, [], tree) })
             }
         }
     };
@@ -484,9 +1254,18 @@ pub enum LenEstimate {
     More(usize)
 }
 
+/// Decides what happens to a key that exists in both trees in [Tree::union_with].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeAction {
+    /// Keep the key with the merged value.
+    Keep,
+    /// Remove the key from both trees.
+    Remove
+}
+
 macro_rules! impl_ReadOnly {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns `true` when the tree is empty.
             #[inline(always)]
             pub fn is_empty(&self) -> bool {
@@ -498,6 +1277,11 @@ macro_rules! impl_ReadOnly {
                 let range = &self.0.meta().range;
                 range[0].is_some() && range[0] == range[1]
             }
+            /// Returns the number of nodes.
+            #[inline(always)]
+            pub fn len(&self) -> usize {
+                self.0.meta().len
+            }
             /// Returns estimation of the number of nodes based on the black-height.
             #[inline]
             pub fn len_estimate(&self) -> LenEstimate {
@@ -524,8 +1308,10 @@ macro_rules! impl_ReadOnly {
                 Some(&self.0[index].key)
             }
             /// Returns the range `min()..=max()`.
+            ///
+            /// This was called `range` before, that name now belongs to the iterator over a range of keys.
             #[inline]
-            pub fn range(&self) -> Option<RangeInclusive<&K>> {
+            pub fn key_range(&self) -> Option<RangeInclusive<&K>> {
                 let [Some(min), Some(max)] = self.0.meta().range else { return None };
                 Some((&self.0[min].key)..=(&self.0[max].key))
             }
@@ -561,27 +1347,19 @@ impl_ReadOnly!(TreeAllocGuard);
 
 macro_rules! impl_ReadWrite {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// # Safety
             /// Calling this function implicitly moves the node pointer into this tree,
             /// using the same pointer in a different tree is undefined behaviour.
             #[inline]
-            pub(crate) unsafe fn insert_node(&mut self, ptr: NodeIndex) -> Result<(), Error> {
+            pub(crate) unsafe fn insert_node(&mut self, ptr: NodeIndex) -> Result<(), Error>
+                where C: Comparator<K>
+            {
                 let key = &self.0[ptr].key;
                 // SAFETY: root is a node in tree
-                match Tree::search(self.0.meta().root, key, &self.0) {
+                match Tree::search(self.0.meta().root, key, self.1.comparator(), &self.0) {
                     SearchResult::Here(_) => return Err(Error::DuplicateKey),
-                    SearchResult::Empty => {
-                        let meta = self.0.meta_mut();
-                        meta.root = Some(ptr);
-                        meta.range = [Some(ptr), Some(ptr)];
-                        meta.black_height = 1;
-                        let node = &mut self.0[ptr];
-                        node.clear(Color::Black);
-                        if V::has_cumulant() {
-                            node.value.update_cumulant([None, None]);
-                        }
-                    },
+                    SearchResult::Empty => self.insert_node_at::<0>(ptr),
                     SearchResult::LeftOf(parent) => {
                         self.0[ptr].clear(Color::Red);
                         // SAFETY: parent is a leaf
@@ -595,6 +1373,19 @@ macro_rules! impl_ReadWrite {
                 }
                 Ok(())
             }
+            /// Inserts the node as the new lowest (`I == 0`) or biggest (`I == 1`) node without comparing keys.
+            ///
+            /// # Safety
+            /// Calling this function implicitly moves the node pointer into this tree,
+            /// using the same pointer in a different tree is undefined behaviour.
+            ///
+            /// The key of the node has to be ordered before/after all keys of this tree.
+            #[inline]
+            pub(crate) unsafe fn insert_node_at<const I: usize>(&mut self, ptr: NodeIndex)
+                where [(); 1 - I]:
+            {
+                Tree::insert_end::<I>(ptr, &mut self.0)
+            }
             /// # Safety
             /// The node pointer has to point to a node in this tree.
             /// This will also implicitly move the node pointer out of this tree,
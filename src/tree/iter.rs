@@ -1,5 +1,5 @@
 use std::{
-    cmp::Ordering, collections::VecDeque, marker::PhantomData, mem::transmute, ptr::read
+    borrow::Borrow, cmp::Ordering, collections::VecDeque, marker::PhantomData, mem::transmute, ops::{Bound, RangeBounds}, ptr::read
 };
 
 #[cfg(feature = "sorted-iter")]
@@ -9,20 +9,20 @@ use crate::{
     discard,
     arena::{Meta, MetaMut, Port, PortAllocGuard},
     tree::{
-        Bounds, Color, Node, NodeIndex, NodeRef, Tree, Value,
+        Bounds, Color, Node, NodeIndex, NodeRef, Tree, Value, Comparator,
         TreeReader, TreeWriter,
         TreeAllocGuard, TreeReadGuard, TreeWriteGuard,
     }
 };
 /// Iterate over all [Tree] nodes in order.
 #[derive(Debug, Clone)]
-pub struct Iter<'a, K: Ord, V, R: TreeReader<K, V>> {
+pub struct Iter<'a, K, V, R: TreeReader<K, V>> {
     pub(crate) tree: &'a R,
     pub(crate) front: NodeRef,
     pub(crate) back: NodeRef,
     pub(crate) _phantom: PhantomData<(K, V)>
 }
-impl<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>> Iterator for Iter<'a, K, V, R> {
+impl<'a, K: 'a, V: Value + 'a, R: TreeReader<K, V>> Iterator for Iter<'a, K, V, R> {
     type Item = (&'a K, V::Ref<'a>);
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -37,7 +37,7 @@ impl<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>> Iterator for Iter<'a,
         Some((&node.key, node.value.get()))
     }
 }
-impl<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>> DoubleEndedIterator for Iter<'a, K, V, R> {
+impl<'a, K: 'a, V: Value + 'a, R: TreeReader<K, V>> DoubleEndedIterator for Iter<'a, K, V, R> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         let current = self.back?;
@@ -52,16 +52,16 @@ impl<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>> DoubleEndedIterator fo
     }
 }
 #[cfg(feature = "sorted-iter")]
-impl<'a, K: Ord, V, R: TreeReader<K, V>> SortedByKey for Iter<'a, K, V, R> {}
+impl<'a, K, V, R: TreeReader<K, V>> SortedByKey for Iter<'a, K, V, R> {}
 /// Iterate over all [Tree] nodes in order.
 #[derive(Debug)]
-pub struct IterMut<'a, K: Ord, V, W: TreeWriter<K, V>> {
+pub struct IterMut<'a, K, V, W: TreeWriter<K, V>> {
     pub(crate) tree: &'a mut W,
     pub(crate) front: NodeRef,
     pub(crate) back: NodeRef,
     pub(crate) _phantom: PhantomData<(K, V)>
 }
-impl<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>> Iterator for IterMut<'a, K, V, W> {
+impl<'a, K: 'a, V: Value + 'a, W: TreeWriter<K, V>> Iterator for IterMut<'a, K, V, W> {
     type Item = (&'a K, V::Mut<'a>);
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -79,7 +79,7 @@ impl<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>> Iterator for IterMut<'
         Some((&node.key, unsafe { node.value.get_mut_unchecked() }))
     }
 }
-impl<'a, K: Ord + 'a, V: 'a, W: TreeWriter<K, V>> DoubleEndedIterator for IterMut<'a, K, V, W> {
+impl<'a, K: 'a, V: 'a, W: TreeWriter<K, V>> DoubleEndedIterator for IterMut<'a, K, V, W> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         let current = self.back?;
@@ -97,8 +97,8 @@ impl<'a, K: Ord + 'a, V: 'a, W: TreeWriter<K, V>> DoubleEndedIterator for IterMu
     }
 }
 #[cfg(feature = "sorted-iter")]
-impl<'a, K: Ord, V, W: TreeWriter<K, V>> SortedByKey for IterMut<'a, K, V, W> {}
-impl<'a, K: Ord, V: Value, W: TreeWriter<K, V>> Drop for IterMut<'a, K, V, W> {
+impl<'a, K, V, W: TreeWriter<K, V>> SortedByKey for IterMut<'a, K, V, W> {}
+impl<'a, K, V: Value, W: TreeWriter<K, V>> Drop for IterMut<'a, K, V, W> {
     #[inline]
     fn drop(&mut self) {
         if V::has_cumulant() {
@@ -113,10 +113,10 @@ impl<'a, K: Ord, V: Value, W: TreeWriter<K, V>> Drop for IterMut<'a, K, V, W> {
 
 /// Iterate over all [Tree] nodes in order.
 #[derive(Debug)]
-pub struct IntoIter<K: Ord, V: Value> {
+pub struct IntoIter<K, V: Value> {
     port: Port<Node<K, V>, Bounds>
 }
-impl<K: Ord, V: Value> Iterator for IntoIter<K, V> {
+impl<K, V: Value> Iterator for IntoIter<K, V> {
     type Item = (K, V::Into);
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -127,6 +127,7 @@ impl<K: Ord, V: Value> Iterator for IntoIter<K, V> {
         // SAFETY: node exists in this tree
         let node = port.remove(index).unwrap();
         let meta = port.meta_mut();
+        meta.len -= 1;
         // SAFETY: either both range bounds are null, or neither
         if meta.range[1].unwrap() == index {
             meta.range = [None; 2];
@@ -135,8 +136,13 @@ impl<K: Ord, V: Value> Iterator for IntoIter<K, V> {
         }
         Some((node.key, node.value.into()))
     }
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.port.read().meta().len;
+        (len, Some(len))
+    }
 }
-impl<K: Ord, V: Value> DoubleEndedIterator for IntoIter<K, V> {
+impl<K, V: Value> DoubleEndedIterator for IntoIter<K, V> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         let mut port = self.port.alloc();
@@ -146,6 +152,7 @@ impl<K: Ord, V: Value> DoubleEndedIterator for IntoIter<K, V> {
         // SAFETY: node exists in this tree
         let node = port.remove(index).unwrap();
         let meta = port.meta_mut();
+        meta.len -= 1;
         // SAFETY: either both range bounds are null, or neither
         if meta.range[0].unwrap() == index {
             meta.range = [None; 2];
@@ -155,31 +162,208 @@ impl<K: Ord, V: Value> DoubleEndedIterator for IntoIter<K, V> {
         Some((node.key, node.value.into()))
     }
 }
-impl<K: Ord, V: Value> ExactSizeIterator for IntoIter<K, V> {}
+impl<K, V: Value> ExactSizeIterator for IntoIter<K, V> {}
+#[cfg(feature = "sorted-iter")]
+impl<K, V: Value> SortedByKey for IntoIter<K, V> {}
+/// Iterate over all [Tree] nodes in order and remove the nodes matching a predicate.
+///
+/// Nodes that were not visited when this is dropped will stay in the tree.
+#[derive(Debug)]
+pub struct ExtractIf<'a, 'b, K, V: Value, F: FnMut(&K, V::Mut<'_>) -> bool> {
+    tree: &'a mut PortAllocGuard<'b, Node<K, V>, Bounds>,
+    next: NodeRef,
+    pred: F
+}
+impl<'a, 'b, K, V: Value, F: FnMut(&K, V::Mut<'_>) -> bool> Iterator for ExtractIf<'a, 'b, K, V, F> {
+    type Item = (K, V::Into);
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(current) = self.next {
+            let node = &mut self.tree[current];
+            let next = node.order[1];
+            // SAFETY: cumulants will be updated on drop
+            if !(self.pred)(&node.key, unsafe { node.value.get_mut_unchecked() }) {
+                self.next = next;
+                continue;
+            }
+            // SAFETY: node is part of tree
+            let ptr = unsafe { Tree::remove_at(current, self.tree) };
+            // NOTE: when the removed node had two children the successor was moved into its place
+            self.next = if ptr == current { next } else { Some(current) };
+            // SAFETY: node was unlinked, so it exists
+            let node = self.tree.remove(ptr).unwrap();
+            return Some((node.key, node.value.into()));
+        }
+        None
+    }
+}
+#[cfg(feature = "sorted-iter")]
+impl<'a, 'b, K, V: Value, F: FnMut(&K, V::Mut<'_>) -> bool> SortedByKey for ExtractIf<'a, 'b, K, V, F> {}
+impl<'a, 'b, K, V: Value, F: FnMut(&K, V::Mut<'_>) -> bool> Drop for ExtractIf<'a, 'b, K, V, F> {
+    #[inline]
+    fn drop(&mut self) {
+        if V::has_cumulant() {
+            let Some(root) = self.tree.meta().root else { return };
+            // SAFETY: root exists and has no ancestors
+            unsafe { Tree::update_cumulants(root, self.tree); }
+        }
+    }
+}
+/// Iterate over all [Tree] nodes inside of a range of keys in order and remove them.
+///
+/// The nodes were already cut out of the tree, each step only frees the next node.
+/// Nodes that were not visited when this is dropped will be removed as well.
+#[derive(Debug)]
+pub struct DrainRange<'a, 'b, K, V: Value> {
+    tree: &'a mut PortAllocGuard<'b, Node<K, V>, Bounds>,
+    next: NodeRef,
+    len: usize
+}
+impl<'a, 'b, K, V: Value> Iterator for DrainRange<'a, 'b, K, V> {
+    type Item = (K, V::Into);
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let current = self.next?;
+        self.next = self.tree[current].order[1];
+        self.len -= 1;
+        // SAFETY: node was detached from the tree, so it exists
+        let node = self.tree.remove(current).unwrap();
+        Some((node.key, node.value.into()))
+    }
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+impl<'a, 'b, K, V: Value> ExactSizeIterator for DrainRange<'a, 'b, K, V> {}
 #[cfg(feature = "sorted-iter")]
-impl<K: Ord, V: Value> SortedByKey for IntoIter<K, V> {}
+impl<'a, 'b, K, V: Value> SortedByKey for DrainRange<'a, 'b, K, V> {}
+impl<'a, 'b, K, V: Value> Drop for DrainRange<'a, 'b, K, V> {
+    #[inline]
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+impl<'a, K, V: Value, C> TreeAllocGuard<'a, K, V, C> {
+    /// Returns an in-order iterator that removes all nodes for which the predicate returns `true`.
+    #[inline]
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, 'a, K, V, F>
+        where F: FnMut(&K, V::Mut<'_>) -> bool
+    {
+        let next = self.0.meta().range[0];
+        ExtractIf { tree: &mut self.0, next, pred }
+    }
+    /// Returns an in-order iterator that removes all nodes inside of the range.
+    ///
+    /// The range is cut out using split/join in `O(log(N))`, the iterator frees one node per step.
+    #[inline]
+    pub fn drain_range<Q, R>(&mut self, range: R) -> DrainRange<'_, 'a, K, V>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, R: RangeBounds<Q>
+    {
+        let root = self.0.meta().root;
+        // SAFETY: root is a node in tree
+        let range = unsafe { Tree::rank_range(root, &range, self.1.comparator(), &self.0) };
+        if range.is_empty() {
+            return DrainRange { tree: &mut self.0, next: None, len: 0 };
+        }
+        // SAFETY: the nodes after the range are appended again and the nodes inside of it are freed by the iterator
+        let inner = unsafe {
+            let right = Tree::split_off(range.end, &mut self.0);
+            let inner = Tree::split_off(range.start, &mut self.0);
+            Tree::append(right, &mut self.0);
+            inner
+        };
+        DrainRange { tree: &mut self.0, next: inner.range[0], len: inner.len }
+    }
+    /// Removes all nodes after the first `n` nodes.
+    ///
+    /// The nodes are split off in `O(log(N))`, freeing the `M` removed nodes takes another `O(M)`.
+    #[inline]
+    pub fn truncate(&mut self, n: usize) {
+        // SAFETY: the split off nodes are freed right away
+        unsafe {
+            let rest = Tree::split_off(n, &mut self.0);
+            Tree::free_detached(rest, &mut self.0);
+        }
+    }
+}
+
+/// Returns the exclusive upper bound of all byte sequences starting with the prefix,
+/// `None` when the prefix is empty or only contains `0xFF`, so there is no upper bound.
+#[inline]
+pub(crate) fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition( |byte| *byte != u8::MAX )?;
+    let mut upper = prefix[..=last].to_vec();
+    upper[last] += 1;
+    Some(upper)
+}
+/// Returns the exclusive upper bound of all strings starting with the prefix,
+/// `None` when the prefix is empty or only contains [char::MAX], so there is no upper bound.
+#[inline]
+pub(crate) fn prefix_upper_bound_str(prefix: &str) -> Option<String> {
+    let (index, last) = prefix.char_indices().rev().find( |(_, c)| *c != char::MAX )?;
+    // NOTE: the only gap in valid chars are the surrogates
+    let next = char::from_u32(last as u32 + 1).unwrap_or('\u{E000}');
+    let mut upper = prefix[..index].to_owned();
+    upper.push(next);
+    Some(upper)
+}
 
 macro_rules! impl_Iter {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns an in-order iterator
             #[inline]
             pub fn iter(&self) -> Iter<K, V, impl TreeReader<K, V> + 'a> {
                 let [front, back] = self.0.meta().range;
                 Iter { tree: &self.0, front, back, _phantom: PhantomData }
             }
-            /// Returns an in-order iterator confined to the given range of keys (inclusive).
+            /// Returns an in-order iterator confined to the given range of keys.
             #[inline]
-            pub fn iter_range<const LI: bool, const RI: bool>(&self, min: &K, max: &K) -> Iter<K, V, impl TreeReader<K, V> + 'a> {
-                // SAFETY: root is a node in tree
-                unsafe {
-                    let meta = self.0.meta();
-                    let front = Tree::closest::<0, LI>(meta.root, min, &self.0)
-                        .or_else( || meta.range[0] );
-                    let back = Tree::closest::<1, RI>(meta.root, max, &self.0)
-                        .or_else( || meta.range[1] );
-                    Iter { tree: &self.0, front, back, _phantom: PhantomData }
-                }
+            pub fn range<Q, R>(&self, range: R) -> Iter<K, V, impl TreeReader<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, R: RangeBounds<Q>
+            {
+                let [front, back] = Tree::range_bounds(&range, self.1.comparator(), &self.0);
+                Iter { tree: &self.0, front, back, _phantom: PhantomData }
+            }
+            /// Returns an in-order iterator over all keys starting with the given prefix.
+            #[inline]
+            pub fn iter_prefix(&self, prefix: &[u8]) -> Iter<K, V, impl TreeReader<K, V> + 'a>
+                where K: Borrow<[u8]>, C: Comparator<[u8]>
+            {
+                let upper = prefix_upper_bound(prefix);
+                let range = (Bound::Included(prefix), upper.as_deref().map_or(Bound::Unbounded, Bound::Excluded));
+                let [front, back] = Tree::range_bounds(&range, self.1.comparator(), &self.0);
+                Iter { tree: &self.0, front, back, _phantom: PhantomData }
+            }
+            /// Returns an in-order iterator over all keys starting with the given prefix.
+            #[inline]
+            pub fn iter_prefix_str(&self, prefix: &str) -> Iter<K, V, impl TreeReader<K, V> + 'a>
+                where K: Borrow<str>, C: Comparator<str>
+            {
+                let upper = prefix_upper_bound_str(prefix);
+                let range = (Bound::Included(prefix), upper.as_deref().map_or(Bound::Unbounded, Bound::Excluded));
+                let [front, back] = Tree::range_bounds(&range, self.1.comparator(), &self.0);
+                Iter { tree: &self.0, front, back, _phantom: PhantomData }
+            }
+            /// Returns an in-order iterator confined to the given range of keys.
+            /// `LI` and `RI` select whether the lower and upper bound are inclusive.
+            ///
+            /// # Note
+            /// The flags used to be inverted, with `true` excluding the bound, see the changelog.
+            #[inline]
+            pub fn iter_range<const LI: bool, const RI: bool>(&self, min: &K, max: &K) -> Iter<K, V, impl TreeReader<K, V> + 'a>
+                where C: Comparator<K>
+            {
+                let range = (
+                    if LI { Bound::Included(min) } else { Bound::Excluded(min) },
+                    if RI { Bound::Included(max) } else { Bound::Excluded(max) }
+                );
+                let [front, back] = Tree::range_bounds(&range, self.1.comparator(), &self.0);
+                Iter { tree: &self.0, front, back, _phantom: PhantomData }
             }
         }
     };
@@ -190,25 +374,36 @@ impl_Iter!(TreeAllocGuard);
 
 macro_rules! impl_IterMut {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns a mutable in-order iterator.
             #[inline]
             pub fn iter_mut(&mut self) -> IterMut<K, V, impl TreeWriter<K, V> + 'a> {
                 let [front, back] = self.0.meta().range;
                 IterMut { tree: &mut self.0, front, back, _phantom: PhantomData }
             }
-            /// Returns a mutable in-order iterator confined to the given range of keys (inclusive).
+            /// Returns a mutable in-order iterator confined to the given range of keys.
             #[inline]
-            pub fn iter_range_mut<const LI: bool, const RI: bool>(&mut self, min: &K, max: &K) -> IterMut<K, V, impl TreeWriter<K, V> + 'a> {
-                // SAFETY: root is a node in tree
-                unsafe {
-                    let meta = self.0.meta();
-                    let front = Tree::closest::<0, LI>(meta.root, min, &self.0)
-                        .or_else( || meta.range[0] );
-                    let back = Tree::closest::<1, RI>(meta.root, max, &self.0)
-                        .or_else( || meta.range[1] );
-                    IterMut { tree: &mut self.0, front, back, _phantom: PhantomData }
-                }
+            pub fn range_mut<Q, R>(&mut self, range: R) -> IterMut<K, V, impl TreeWriter<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, R: RangeBounds<Q>
+            {
+                let [front, back] = Tree::range_bounds(&range, self.1.comparator(), &self.0);
+                IterMut { tree: &mut self.0, front, back, _phantom: PhantomData }
+            }
+            /// Returns a mutable in-order iterator confined to the given range of keys.
+            /// `LI` and `RI` select whether the lower and upper bound are inclusive.
+            ///
+            /// # Note
+            /// The flags used to be inverted, with `true` excluding the bound, see the changelog.
+            #[inline]
+            pub fn iter_range_mut<const LI: bool, const RI: bool>(&mut self, min: &K, max: &K) -> IterMut<K, V, impl TreeWriter<K, V> + 'a>
+                where C: Comparator<K>
+            {
+                let range = (
+                    if LI { Bound::Included(min) } else { Bound::Excluded(min) },
+                    if RI { Bound::Included(max) } else { Bound::Excluded(max) }
+                );
+                let [front, back] = Tree::range_bounds(&range, self.1.comparator(), &self.0);
+                IterMut { tree: &mut self.0, front, back, _phantom: PhantomData }
             }
         }
     };
@@ -218,7 +413,7 @@ impl_IterMut!(TreeAllocGuard);
 
 macro_rules! impl_IntoIterator_Ref {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> IntoIterator for &'a $type <'a, K, V> {
+        impl<'a, K, V: Value, C> IntoIterator for &'a $type <'a, K, V, C> {
             type IntoIter = Iter<'a, K, V, impl TreeReader<K, V> + 'a>;
             type Item = <Self::IntoIter as Iterator>::Item;
             #[inline]
@@ -235,7 +430,7 @@ impl_IntoIterator_Ref!(TreeAllocGuard);
 
 macro_rules! impl_IntoIterator_Mut {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> IntoIterator for &'a mut $type <'a, K, V> {
+        impl<'a, K, V: Value, C> IntoIterator for &'a mut $type <'a, K, V, C> {
             type IntoIter = IterMut<'a, K, V, impl TreeWriter<K, V> + 'a>;
             type Item = <Self::IntoIter as Iterator>::Item;
             #[inline]
@@ -250,7 +445,7 @@ macro_rules! impl_IntoIterator_Mut {
 impl_IntoIterator_Mut!(TreeWriteGuard);
 impl_IntoIterator_Mut!(TreeAllocGuard);
 
-impl<K: Ord, V: Value> IntoIterator for Tree<K, V> {
+impl<K, V: Value, C> IntoIterator for Tree<K, V, C> {
     type IntoIter = IntoIter<K, V>;
     type Item = <Self::IntoIter as Iterator>::Item;
     #[inline(always)]
@@ -259,15 +454,13 @@ impl<K: Ord, V: Value> IntoIterator for Tree<K, V> {
     }
 }
 
-impl<K: Ord, V: Value> Tree<K, V> {
+impl<K, V: Value, C> Tree<K, V, C> {
     /// # Safety
-    /// It is assumed that the given iterator is sorted by K in incresing order.
-    /// Port->meta is expected to be set to its default value
-    ///
-    /// For a safe version of this function use the 'sorted-iter' feature.
+    /// It is assumed that the given vector is sorted by K in incresing order.
+    /// The tree is expected to be empty
     #[inline]
-    pub(crate) unsafe fn from_sorted_slice_unchecked(port: Port<Node<K, V>, Bounds>, items: &[(K, V::Local)]) -> Self {
-        fn build_tree<K: Ord, V: Value>(
+    pub(crate) unsafe fn from_sorted_vec_unchecked(tree: Self, mut vec: Vec<(K, V::Local)>) -> Self {
+        fn build_tree<K, V: Value>(
             port: &mut PortAllocGuard<Node<K, V>, Bounds>,
             items: &[(K, V::Local)], parent: NodeRef, color: Color
         ) -> [NodeRef; 3]
@@ -320,21 +513,26 @@ impl<K: Ord, V: Value> Tree<K, V> {
             ]
         }
 
-        let len = items.len();
+        let len = vec.len();
         if len == 0 {
-            return Self { port }
+            return tree
         }
+        // NOTE: the items are moved out one by one, when this panics the remaining items are leaked instead of dropped twice
+        vec.set_len(0);
+        // SAFETY: the first len items are initialized and the allocation is kept alive by vec
+        let items = std::slice::from_raw_parts(vec.as_ptr(), len);
         let height = usize::BITS - len.leading_zeros();
         let color = if height & 1 == 0 { Color::Black }
             else { Color::Red };
         {
-            let mut port = port.alloc();
+            let mut port = tree.port.alloc();
             // NOTE: recursion depth = height + 1
             let [min, root, max] = build_tree(&mut port, items, None, color);
             let meta = port.meta_mut();
             meta.root = root;
             meta.range = [min, max];
             meta.black_height = (height >> 1) as u8;
+            meta.len = len;
             if let Some(root) = root {
                 let node = &mut port[root];
                 if node.is_red() {
@@ -343,17 +541,81 @@ impl<K: Ord, V: Value> Tree<K, V> {
                 }
             }
         }
-        Self { port }
+        tree
     }
-    pub(crate) unsafe fn from_sorted_iter_unchecked(port: Port<Node<K, V>, Bounds>, iter: impl IntoIterator<Item = (K, V::Local)>) -> Self {
-        let items = iter.into_iter().collect::<Box<[_]>>();
-        Self::from_sorted_slice_unchecked(port, &items)
+    /// # Safety
+    /// It is assumed that the given iterator is sorted by K in incresing order.
+    /// The tree is expected to be empty
+    ///
+    /// For a safe version of this function use the 'sorted-iter' feature.
+    #[inline]
+    pub(crate) unsafe fn from_sorted_iter_unchecked(tree: Self, iter: impl IntoIterator<Item = (K, V::Local)>) -> Self {
+        Self::from_sorted_vec_unchecked(tree, iter.into_iter().collect())
     }
-    #[cfg(feature = "sorted-iter")]
-    #[inline(always)]
-    pub(crate) fn from_sorted_iter(port: Port<Node<K, V>, Bounds>, iter: impl IntoIterator<Item = (K, V::Local)> + SortedByKey) -> Self {
-        // SAFETY: guarantied by trait
-        unsafe { Self::from_sorted_iter_unchecked(port, iter) }
+}
+impl<K, V: Value> Tree<K, V> {
+    /// Links existing nodes into a balanced tree, this will replace the previous structure of the tree.
+    ///
+    /// # Safety
+    /// It is assumed that the given nodes are sorted by K in increasing order.
+    ///
+    /// The node pointers have to be owned by tree and the tree cannot own any other nodes after this.
+    #[inline]
+    pub(crate) unsafe fn relink_sorted_unchecked(nodes: &[NodeIndex],
+        tree: &mut impl TreeWriter<K, V>
+    ) {
+        fn build_tree<K, V: Value>(
+            tree: &mut impl TreeWriter<K, V>,
+            nodes: &[NodeIndex], parent: NodeRef, color: Color
+        ) -> [NodeRef; 3]
+        {
+            let len = nodes.len();
+            if len == 0 {
+                return [None, None, None];
+            }
+            let pivot = len >> 1;
+            let index = nodes[pivot];
+            let this = Some(index);
+            let [min, left, prev] = build_tree(tree, &nodes[..pivot], this, !color);
+            let [next, right, max] = build_tree(tree, &nodes[(pivot + 1)..], this, !color);
+            let root = &mut tree[index];
+            root.color = color;
+            root.parent = parent;
+            root.children = [left, right];
+            root.order = [prev, next];
+            discard! {
+                tree[prev?].order[1] = this
+            };
+            discard! {
+                tree[next?].order[0] = this
+            };
+            // SAFETY: the nodes will be linked bottom-up, so the children are final
+            unsafe { Tree::update_cumulant(index, tree) };
+            [
+                if pivot == 0 { this } else { min },
+                this,
+                if pivot == len - 1 { this } else { max }
+            ]
+        }
+
+        let len = nodes.len();
+        let height = usize::BITS - len.leading_zeros();
+        let color = if height & 1 == 0 { Color::Black }
+            else { Color::Red };
+        // NOTE: recursion depth = height
+        let [min, root, max] = build_tree(tree, nodes, None, color);
+        let meta = tree.meta_mut();
+        meta.root = root;
+        meta.range = [min, max];
+        meta.black_height = (height >> 1) as u8;
+        meta.len = len;
+        if let Some(root) = root {
+            let node = &mut tree[root];
+            if node.is_red() {
+                node.color = Color::Black;
+                tree.meta_mut().black_height += 1;
+            }
+        }
     }
 }
 /// Possible action after a node visit during a binary search.
@@ -433,13 +695,13 @@ impl const From<Ordering> for SearchAction {
 /// assert_eq!(filtered, vec![1,3,5]);
 /// ```
 #[derive(Debug, Clone)]
-pub struct Filter<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> {
+pub struct Filter<'a, K: 'a, V: Value + 'a, R: TreeReader<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> {
     pub(crate) tree: &'a R,
     pub(crate) stack: VecDeque<NodeIndex>,
     pub(crate) action: F,
     pub(crate) _phantom: PhantomData<(K, V)>
 }
-impl<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> Filter<'a, K, V, R, F> {
+impl<'a, K: 'a, V: Value + 'a, R: TreeReader<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> Filter<'a, K, V, R, F> {
     #[inline]
     fn step(&mut self) -> Option<Option<<Self as Iterator>::Item>> {
         let ptr = self.stack.pop_back()?;
@@ -458,7 +720,7 @@ impl<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>, F: Fn(&K, V::Ref<'_>)
         }
     }
 }
-impl<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> Iterator for Filter<'a, K, V, R, F> {
+impl<'a, K: 'a, V: Value + 'a, R: TreeReader<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> Iterator for Filter<'a, K, V, R, F> {
     type Item = (&'a K, V::Ref<'a>);
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -485,13 +747,13 @@ impl<'a, K: Ord + 'a, V: Value + 'a, R: TreeReader<K, V>, F: Fn(&K, V::Ref<'_>)
 /// assert_eq!(write.iter().map( |(_, i)| *i ).collect::<Vec<_>>(), vec![0,2,2,6,4]);
 /// ```
 #[derive(Debug)]
-pub struct FilterMut<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> {
+pub struct FilterMut<'a, K: 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> {
     pub(crate) tree: &'a mut W,
     pub(crate) stack: VecDeque<NodeIndex>,
     pub(crate) action: F,
     pub(crate) _phantom: PhantomData<(K, V)>
 }
-impl<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> FilterMut<'a, K, V, W, F> {
+impl<'a, K: 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> FilterMut<'a, K, V, W, F> {
     #[inline]
     fn step(&mut self) -> Option<Option<<Self as Iterator>::Item>> {
         let ptr = self.stack.pop_back()?;
@@ -511,7 +773,7 @@ impl<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>)
         }
     }
 }
-impl<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> Iterator for FilterMut<'a, K, V, W, F> {
+impl<'a, K: 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> Iterator for FilterMut<'a, K, V, W, F> {
     type Item = (&'a K, V::Mut<'a>);
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -524,7 +786,7 @@ impl<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>)
     }
 }
 // TODO: this will update all cumulants, even when filter did exit early
-impl<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> Drop for FilterMut<'a, K, V, W, F> {
+impl<'a, K: 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>) -> SearchAction> Drop for FilterMut<'a, K, V, W, F> {
     #[inline]
     fn drop(&mut self) {
         if V::has_cumulant() {
@@ -536,7 +798,7 @@ impl<'a, K: Ord + 'a, V: Value + 'a, W: TreeWriter<K, V>, F: Fn(&K, V::Ref<'_>)
 
 macro_rules! impl_Filter {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns an breath-first iterator, filtered by a function.
             #[inline]
             pub fn filter<F: Fn(&K, V::Ref<'_>) -> SearchAction>(&self, action: F) -> Filter<K, V, impl TreeReader<K, V> + 'a, F> {
@@ -555,7 +817,7 @@ impl_Filter!(TreeAllocGuard);
 
 macro_rules! impl_FilterMut {
     ( $type:ident ) => {
-        impl<'a, K: Ord, V: Value> $type <'a, K, V> {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
             /// Returns an mutable breath-first iterator, filtered by a function.
             #[inline]
             pub fn filter_mut<F: Fn(&K, V::Ref<'_>) -> SearchAction>(&mut self, action: F) -> FilterMut<K, V, impl TreeWriter<K, V> + 'a, F> {
@@ -6,11 +6,19 @@ mod iter;
 pub use iter::*;
 mod cursor;
 pub use cursor::*;
+mod entry;
+pub use entry::*;
+mod comparator;
+pub use comparator::*;
+mod multi;
+pub use multi::*;
 
 use std::{
+    borrow::Borrow,
     cmp::Ordering,
-    mem::swap,
-    ops::{Index as IndexRO, IndexMut}
+    mem::{replace, swap, take},
+    ops::{Bound, Index as IndexRO, IndexMut, Range, RangeBounds},
+    sync::Arc
 };
 
 use thiserror::Error;
@@ -18,14 +26,14 @@ use thiserror::Error;
 use crate::{
     discard,
     Reader, Writer,
-    arena::{Port, Index, Meta, MetaMut, Error as ArenaError},
+    arena::{Port, PortAllocGuard, Index, Meta, MetaMut, Error as ArenaError},
 };
 
 // SAFETY: these have to be public for generic bounds only, there is no way to access an actual object of this type publically
 #[allow(private_bounds)]
-pub trait TreeReader<K: Ord, V: Value> = Reader<Index, Item = Node<K, V>> + IndexRO<NodeIndex, Output = Node<K, V>> + Meta<Type = Bounds>;
+pub trait TreeReader<K, V: Value> = Reader<Index, Item = Node<K, V>> + IndexRO<NodeIndex, Output = Node<K, V>> + Meta<Type = Bounds>;
 #[allow(private_bounds)]
-pub trait TreeWriter<K: Ord, V: Value> = Writer<Index, ArenaError, Item = Node<K, V>> + IndexMut<NodeIndex, Output = Node<K, V>> + MetaMut<Type = Bounds>;
+pub trait TreeWriter<K, V: Value> = Writer<Index, ArenaError, Item = Node<K, V>> + IndexMut<NodeIndex, Output = Node<K, V>> + MetaMut<Type = Bounds>;
 
 
 #[derive_const(Debug, Error)]
@@ -37,7 +45,11 @@ pub enum Error {
     #[error(transparent)]
     Arena(#[from] ArenaError),
     #[error("can only join disjoint trees")]
-    Overlapping
+    Overlapping,
+    #[error("key does not exist")]
+    MissingKey,
+    #[error("trees have to belong to the same forest")]
+    ForeignTree
 }
 /// Possible results of a binary search in a [Tree].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -81,23 +93,41 @@ impl<T> SearchResult<T> {
 pub(crate) struct Bounds {
     pub root: NodeRef,
     pub range: [NodeRef; 2],
-    pub black_height: u8
+    pub black_height: u8,
+    pub len: usize
 }
 /// Red-Black Tree data structure.
 ///
 /// This provides in-order iteration in `O(N)` and split/join in `O(log(N))`.
 ///
 /// Each node can hold data by implementing the [Value] trait.
+/// The order of the keys is defined by a [Comparator] shared with all trees of the same forest.
+///
+/// Operations taking multiple trees require all of them to belong to the same forest,
+/// otherwise the inputs are returned unchanged with [Error::ForeignTree].
 #[derive(Debug)]
-pub struct Tree<K: Ord, V: Value> {
-    port: Port<Node<K, V>, Bounds>
+pub struct Tree<K, V: Value, C = OrdComparator> {
+    port: Port<Node<K, V>, Bounds>,
+    cmp: Arc<C>
 }
-impl<K: Ord, V: Value> Tree<K, V> {
+impl<K, V: Value, C> Tree<K, V, C> {
+    #[inline(always)]
+    pub(crate) fn new(port: Port<Node<K, V>, Bounds>, cmp: Arc<C>) -> Self {
+        Self { port, cmp }
+    }
+    /// Creates a new tree with the given bounds, sharing the arena and comparator of this tree.
     #[inline(always)]
-    pub(crate) fn new(port: Port<Node<K, V>, Bounds>) -> Self {
-        Self { port }
+    pub(crate) fn split_with_meta(&self, meta: Bounds) -> Self {
+        Self::new(self.port.split_with_meta(meta), self.cmp.clone())
     }
-    /// Calculate only the cumulant on the given node.
+    /// Returns the comparator defining the order of the keys.
+    #[inline(always)]
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+}
+impl<K, V: Value> Tree<K, V> {
+    /// Calculate only the cumulant and the sub-tree size on the given node.
     ///
     /// # Safety
     /// After this all ancestors of the node have invalid cumulants.
@@ -111,9 +141,12 @@ impl<K: Ord, V: Value> Tree<K, V> {
     ) {
         let [left, right] = tree[ptr].children;
         let (Some(node), [left, right]) = tree.get_mut_with(ptr, [left, right]).unwrap() else { panic!() };
-        let left = left.map( |left| left.value.cumulant() );
-        let right = right.map( |right| right.value.cumulant() );
-        node.value.update_cumulant([left, right]);
+        node.size = 1 + left.map_or(0, |left| left.size) + right.map_or(0, |right| right.size);
+        if V::has_cumulant() {
+            let left = left.map( |left| left.value.cumulant() );
+            let right = right.map( |right| right.value.cumulant() );
+            node.value.update_cumulant([left, right]);
+        }
     }
     /// Calculate cumulants starting from the given node and updating all ancestors
     ///
@@ -140,7 +173,7 @@ impl<K: Ord, V: Value> Tree<K, V> {
     unsafe fn update_cumulants(ptr: NodeIndex,
         tree: &mut impl TreeWriter<K, V>
     ) {
-        fn helper<K: Ord, V: Value>(ptr: NodeIndex,
+        fn helper<K, V: Value>(ptr: NodeIndex,
             tree: &mut impl TreeWriter<K, V>
         ) -> *const V::Cumulant {
             let [left, right] = tree[ptr].children;
@@ -177,6 +210,38 @@ impl<K: Ord, V: Value> Tree<K, V> {
             tree.meta_mut().root = new;
         }
     }
+    /// Moves a node into an unlinked node pointer, the keys and values of both nodes are swapped.
+    ///
+    /// # Safety
+    /// Both node pointers have to be owned by tree, but only `from` can be linked into it.
+    #[inline]
+    unsafe fn relocate(from: NodeIndex, to: NodeIndex,
+        tree: &mut impl TreeWriter<K, V>
+    ) {
+        // SAFETY: both nodes exist and are not the same
+        let Ok([Some(from_node), Some(to_node)]) = tree.get_pair_mut(from, to)
+            else { panic!() };
+        swap(&mut from_node.key, &mut to_node.key);
+        swap(&mut from_node.value, &mut to_node.value);
+        to_node.color = from_node.color;
+        to_node.children = from_node.children;
+        to_node.order = from_node.order;
+        to_node.size = from_node.size;
+        let [children, order] = [from_node.children, from_node.order];
+        let ptr = Some(to);
+        Self::replace(from, ptr, tree);
+        for child in children.into_iter().flatten() {
+            tree[child].parent = ptr;
+        }
+        match order[0] {
+            Some(prev) => tree[prev].order[1] = ptr,
+            None => tree.meta_mut().range[0] = ptr
+        }
+        match order[1] {
+            Some(next) => tree[next].order[0] = ptr,
+            None => tree.meta_mut().range[1] = ptr
+        }
+    }
     /// # Safety
     /// the node at `ptr->children[1 - I]` cannot be None.
     ///
@@ -196,18 +261,18 @@ impl<K: Ord, V: Value> Tree<K, V> {
         discard! {
             tree[child?].parent = Some(ptr)
         };
-        if V::has_cumulant() {
-            Self::update_cumulant(ptr, tree);
-        }
+        Self::update_cumulant(ptr, tree);
     }
     /// # Safety
     /// The node pointer has to be owned by tree.
     #[inline(always)]
-    unsafe fn search(ptr: NodeRef, key: &K,
+    unsafe fn search<Q, C>(ptr: NodeRef, key: &Q, cmp: &C,
         tree: &impl TreeReader<K, V>
-    ) -> SearchResult<NodeIndex> {
+    ) -> SearchResult<NodeIndex>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
         // SAFETY: ordering is guarantied by definition
-        Self::search_by(ptr, |node| node.key.cmp(key), tree)
+        Self::search_by(ptr, |node| cmp.compare(node.key.borrow(), key), tree)
     }
     /// The result of this is meaningless,
     /// unless the tree is ordered by `compare`
@@ -274,15 +339,14 @@ impl<K: Ord, V: Value> Tree<K, V> {
             Some(far) => tree[far].order[1 - I] = Some(ptr),
             None => tree.meta_mut().range[I] = Some(ptr)
         }
+        tree.meta_mut().len += 1;
         let parent_node = &mut tree[parent];
         parent_node.children[I] = Some(ptr);
         parent_node.order[I] = Some(ptr);
         if parent_node.parent.is_some() {
             Self::fix_insert(ptr, tree);
         }
-        if V::has_cumulant() {
-            Self::propagate_cumulant(ptr, tree);
-        }
+        Self::propagate_cumulant(ptr, tree);
     }
     /// # Safety
     /// The node at `ptr->parent->parent` cannot be None.
@@ -291,7 +355,7 @@ impl<K: Ord, V: Value> Tree<K, V> {
         tree: &mut impl TreeWriter<K, V>
     ) {
         #[inline]
-        unsafe fn helper<const I: usize, const J: usize, K: Ord, V: Value>(mut ptr: NodeIndex, parent: NodeIndex, grandparent: NodeIndex,
+        unsafe fn helper<const I: usize, const J: usize, K, V: Value>(mut ptr: NodeIndex, parent: NodeIndex, grandparent: NodeIndex,
             tree: &mut impl TreeWriter<K, V>
         ) -> NodeIndex
             where [(); 1 - I]:, [(); 1 - J]:, [(); 1 - (1 - I)]:
@@ -406,6 +470,7 @@ impl<K: Ord, V: Value> Tree<K, V> {
             // SAFETY: case of both children was transformed into max one child earlier
             _ => panic!()
         }
+        tree.meta_mut().len -= 1;
         match prev {
             Some(prev) => tree[prev].order[1] = next,
             None => tree.meta_mut().range[0] = next
@@ -415,9 +480,7 @@ impl<K: Ord, V: Value> Tree<K, V> {
             None => tree.meta_mut().range[1] = prev
         }
         if let Some(parent) = parent {
-            if V::has_cumulant() {
-                Self::propagate_cumulant(parent, tree);
-            }
+            Self::propagate_cumulant(parent, tree);
         }
         ptr
     }
@@ -431,7 +494,7 @@ impl<K: Ord, V: Value> Tree<K, V> {
         tree: &mut impl TreeWriter<K, V>
     ) {
         #[inline]
-        unsafe fn helper<const I: usize, K: Ord, V: Value>(parent: NodeIndex,
+        unsafe fn helper<const I: usize, K, V: Value>(parent: NodeIndex,
             tree: &mut impl TreeWriter<K, V>
         ) -> NodeRef
             where [(); 1 - I]:, [(); 1 - (1 - I)]:
@@ -500,6 +563,108 @@ impl<K: Ord, V: Value> Tree<K, V> {
             } else { return; }
         }
     }
+    /// Returns the number of nodes in the sub-tree rooted at the given node.
+    #[inline(always)]
+    fn size(ptr: NodeRef,
+        tree: &impl TreeReader<K, V>
+    ) -> usize {
+        ptr.map_or(0, |ptr| tree[ptr].size)
+    }
+    /// Returns the in-order position of the given key,
+    /// or the position it would be inserted at when the key does not exist.
+    ///
+    /// When there are multiple nodes with this key, the position of the first one is returned.
+    ///
+    /// # Safety
+    /// The node pointer has to be owned by tree.
+    #[inline]
+    unsafe fn rank<Q, C>(mut ptr: NodeRef, key: &Q, cmp: &C,
+        tree: &impl TreeReader<K, V>
+    ) -> Result<usize, usize>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
+        let (mut rank, mut found) = (0, false);
+        while let Some(index) = ptr {
+            let node = &tree[index];
+            match cmp.compare(node.key.borrow(), key) {
+                Ordering::Less => {
+                    rank += Self::size(node.children[0], tree) + 1;
+                    ptr = node.children[1];
+                },
+                order => {
+                    found |= order.is_eq();
+                    ptr = node.children[0];
+                }
+            }
+        }
+        if found { Ok(rank) } else { Err(rank) }
+    }
+    /// Returns the number of nodes with a key less than or equal to the given key.
+    ///
+    /// # Safety
+    /// The node pointer has to be owned by tree.
+    #[inline]
+    unsafe fn rank_after<Q, C>(mut ptr: NodeRef, key: &Q, cmp: &C,
+        tree: &impl TreeReader<K, V>
+    ) -> usize
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
+        let mut rank = 0;
+        while let Some(index) = ptr {
+            let node = &tree[index];
+            if cmp.compare(node.key.borrow(), key).is_le() {
+                rank += Self::size(node.children[0], tree) + 1;
+                ptr = node.children[1];
+            } else {
+                ptr = node.children[0];
+            }
+        }
+        rank
+    }
+    /// Returns the node at the given in-order position.
+    ///
+    /// # Safety
+    /// The node pointer has to be owned by tree.
+    #[inline]
+    unsafe fn select(mut ptr: NodeRef, mut index: usize,
+        tree: &impl TreeReader<K, V>
+    ) -> NodeRef {
+        while let Some(current) = ptr {
+            let node = &tree[current];
+            let left = Self::size(node.children[0], tree);
+            match index.cmp(&left) {
+                Ordering::Less => ptr = node.children[0],
+                Ordering::Equal => return Some(current),
+                Ordering::Greater => {
+                    index -= left + 1;
+                    ptr = node.children[1];
+                }
+            }
+        }
+        None
+    }
+    /// Returns the in-order positions of all nodes inside of the range.
+    ///
+    /// # Safety
+    /// The node pointer has to be owned by tree.
+    #[inline]
+    unsafe fn rank_range<Q, R, C>(ptr: NodeRef, range: &R, cmp: &C,
+        tree: &impl TreeReader<K, V>
+    ) -> Range<usize>
+        where K: Borrow<Q>, Q: ?Sized, R: RangeBounds<Q>, C: Comparator<Q>
+    {
+        let start = match range.start_bound() {
+            Bound::Included(key) => Self::rank(ptr, key, cmp, tree).unwrap_or_else( |rank| rank ),
+            Bound::Excluded(key) => Self::rank_after(ptr, key, cmp, tree),
+            Bound::Unbounded => 0
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => Self::rank_after(ptr, key, cmp, tree),
+            Bound::Excluded(key) => Self::rank(ptr, key, cmp, tree).unwrap_or_else( |rank| rank ),
+            Bound::Unbounded => Self::size(ptr, tree)
+        };
+        start..end.max(start)
+    }
     #[inline]
     fn limit<const I: usize>(mut ptr: NodeIndex,
         tree: &impl TreeReader<K, V>
@@ -511,25 +676,66 @@ impl<K: Ord, V: Value> Tree<K, V> {
         }
         ptr
     }
+    /// Returns the closest node to the key, searching smaller keys for `I == 0` and greater keys for `I == 1`.
+    /// A node with exactly this key is only returned when `INCLUSIVE` is `true`.
+    ///
+    /// Runs of equal keys are included or excluded as a whole.
+    ///
     /// # Safety
     /// The node pointer has to be owned by tree.
     #[inline]
-    unsafe fn closest<const I: usize, const INCLUSIVE: bool>(ptr: NodeRef, key: &K,
+    unsafe fn closest<const I: usize, const INCLUSIVE: bool, Q, C>(ptr: NodeRef, key: &Q, cmp: &C,
         tree: &impl TreeReader<K, V>
     ) -> NodeRef
-        where [(); 1 - I]:
+        where [(); 1 - I]:, K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
     {
-        match Self::search(ptr, key, tree) {
-            SearchResult::Empty => None,
-            SearchResult::Here(node) =>
-                if INCLUSIVE { tree[node].order[I] }
-                else { Some(node) },
+        // NOTE: equal keys are treated as smaller or greater than the key, so the search always ends next to them
+        let equal_is_less = INCLUSIVE == (I == 0);
+        let compare = |node: &Node<K, V>| match cmp.compare(node.key.borrow(), key) {
+            Ordering::Equal if equal_is_less => Ordering::Less,
+            Ordering::Equal => Ordering::Greater,
+            order => order
+        };
+        match Self::search_by(ptr, compare, tree) {
+            SearchResult::Empty | SearchResult::Here(_) => None,
             SearchResult::LeftOf(node) =>
-                if I == 0 { Some(node) }
-                else { tree[node].order[1] },
-            SearchResult::RightOf(node) =>
                 if I == 1 { Some(node) }
-                else { tree[node].order[0] }
+                else { tree[node].order[0] },
+            SearchResult::RightOf(node) =>
+                if I == 0 { Some(node) }
+                else { tree[node].order[1] }
+        }
+    }
+    /// Returns the closest node inside of the bound, searching smaller keys for `I == 0` and greater keys for `I == 1`.
+    #[inline]
+    fn closest_bound<const I: usize, Q, C>(bound: Bound<&Q>, cmp: &C,
+        tree: &impl TreeReader<K, V>
+    ) -> NodeRef
+        where [(); 1 - I]:, K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
+        let meta = tree.meta();
+        // SAFETY: root is a node in tree
+        unsafe {
+            match bound {
+                Bound::Included(key) => Self::closest::<I, true, _, _>(meta.root, key, cmp, tree),
+                Bound::Excluded(key) => Self::closest::<I, false, _, _>(meta.root, key, cmp, tree),
+                Bound::Unbounded => meta.range[1 - I]
+            }
+        }
+    }
+    /// Returns the first and last node inside of the range,
+    /// both are `None` when there is no node inside of the range.
+    #[inline]
+    fn range_bounds<Q, R, C>(range: &R, cmp: &C,
+        tree: &impl TreeReader<K, V>
+    ) -> [NodeRef; 2]
+        where K: Borrow<Q>, Q: ?Sized, R: RangeBounds<Q>, C: Comparator<Q>
+    {
+        let front = Self::closest_bound::<1, _, _>(range.start_bound(), cmp, tree);
+        let back = Self::closest_bound::<0, _, _>(range.end_bound(), cmp, tree);
+        match (front, back) {
+            (Some(min), Some(max)) if cmp.compare(tree[min].key.borrow(), tree[max].key.borrow()).is_le() => [front, back],
+            _ => [None, None]
         }
     }
     /// # Note
@@ -542,10 +748,10 @@ impl<K: Ord, V: Value> Tree<K, V> {
     #[inline]
     unsafe fn join_unchecked<const I: usize>(
         this: &mut impl TreeWriter<K, V>,
-        pivot: NodeIndex, that: Port<Node<K, V>, Bounds>
+        pivot: NodeIndex, that_meta: Bounds
     ) where [(); 1 - I]: {
         #[inline]
-        unsafe fn helper<const I: usize, K: Ord, V: Value>(
+        unsafe fn helper<const I: usize, K, V: Value>(
             this: &mut impl TreeWriter<K, V>,
             parent: NodeRef, this_child: NodeRef,
             pivot: NodeIndex, that_meta: Bounds
@@ -577,6 +783,7 @@ impl<K: Ord, V: Value> Tree<K, V> {
             };
             let this_meta = this.meta_mut();
             this_meta.range[1 - I] = that_meta.range[1 - I];
+            this_meta.len += that_meta.len + 1;
             if let Some(parent) = parent {
                 if this[parent].parent.is_some() {
                     Tree::fix_insert(pivot, this);
@@ -586,13 +793,10 @@ impl<K: Ord, V: Value> Tree<K, V> {
                 this_meta.black_height += 1;
                 this[pivot].color = Color::Black;
             }
-            if V::has_cumulant() {
-                Tree::propagate_cumulant(pivot, this);
-            }
+            Tree::propagate_cumulant(pivot, this);
         }
         let ptr = Some(pivot);
         let this_meta = this.meta();
-        let that_meta = that.free();
         // SAFETY: this is never negative
         let mut diff = this_meta.black_height - that_meta.black_height;
         if diff == 0 {
@@ -606,16 +810,189 @@ impl<K: Ord, V: Value> Tree<K, V> {
             if node.is_black() {
                 diff -= 1;
             }
-            if let Some(next) = node.children[1 - I] {
-                index = next;
-            } else {
-                // SAFETY: join point will be found before node is null
-                index = node.children[I].unwrap();
-            }
+            // SAFETY: join point will be found before node is null
+            index = node.children[1 - I].unwrap();
+        }
+        // NOTE: the join point has to be black, a red node has black children with the same black-height
+        if this[index].is_red() {
+            // SAFETY: black-height of the join point is at least one, so the children exist
+            index = this[index].children[1 - I].unwrap();
         }
         Self::replace(index, ptr, this);
         helper::<I, K, V>(this, this[index].parent, Some(index), pivot, that_meta);
     }
+    /// Insert a node before the lowest node for `I == 0` or after the biggest node for `I == 1`.
+    ///
+    /// # Safety
+    /// The node has to be owned by tree and must not be part of any tree.
+    ///
+    /// The key of the node has to be ordered before/after all keys of this tree.
+    #[inline]
+    unsafe fn insert_end<const I: usize>(ptr: NodeIndex,
+        tree: &mut impl TreeWriter<K, V>
+    ) where [(); 1 - I]: {
+        match tree.meta().range[I] {
+            Some(parent) => {
+                tree[ptr].clear(Color::Red);
+                // SAFETY: the lowest/biggest node has no child in this direction
+                Self::insert_at::<I>(ptr, parent, tree)
+            },
+            None => {
+                let meta = tree.meta_mut();
+                meta.root = Some(ptr);
+                meta.range = [Some(ptr), Some(ptr)];
+                meta.black_height = 1;
+                meta.len = 1;
+                let node = &mut tree[ptr];
+                node.clear(Color::Black);
+                if V::has_cumulant() {
+                    node.value.update_cumulant([None, None]);
+                }
+            }
+        }
+    }
+    /// Split the tree at its root, the tree keeps the left sub-tree.
+    /// Returns the root and the bounds of the right sub-tree.
+    ///
+    /// # Safety
+    /// The root and the right sub-tree are no longer part of the tree, they have to be joined back or freed.
+    #[inline]
+    unsafe fn split_root(tree: &mut impl TreeWriter<K, V>) -> (NodeRef, Bounds) {
+        let Some(index) = tree.meta().root
+            else { return (None, Bounds::default()) };
+        let node = &mut tree[index];
+        let children = take(&mut node.children);
+        let order = take(&mut node.order);
+        node.size = 1;
+        let sizes = children.map( |child| Self::size(child, tree) );
+        let left_bounds = tree.meta_mut();
+        let mut right_bounds = *left_bounds;
+        if let Some(index) = children[0] {
+            left_bounds.root = children[0];
+            left_bounds.range[1] = order[0];
+            left_bounds.len = sizes[0];
+            discard! {
+                tree[order[0]?].order[1] = None
+            };
+            let root = &mut tree[index];
+            root.parent = None;
+            if root.is_red() {
+                root.color = Color::Black;
+            } else {
+                tree.meta_mut().black_height -= 1;
+            }
+        } else {
+            *left_bounds = Bounds::default();
+        }
+        if let Some(index) = children[1] {
+            right_bounds.root = children[1];
+            right_bounds.range[0] = order[1];
+            right_bounds.len = sizes[1];
+            discard! {
+                tree[order[1]?].order[0] = None
+            };
+            let root = &mut tree[index];
+            root.parent = None;
+            if root.is_red() {
+                root.color = Color::Black;
+            } else {
+                right_bounds.black_height -= 1;
+            }
+        } else {
+            right_bounds = Bounds::default();
+        }
+        (Some(index), right_bounds)
+    }
+    /// Join the tree with the pivot and the other tree, all keys of the other tree are ordered after the pivot.
+    ///
+    /// # Safety
+    /// The pivot and all nodes of the other tree have to be owned by tree and will be moved into the tree.
+    ///
+    /// `tree->max->key <= pivot->key <= other->min->key`
+    #[inline]
+    unsafe fn join_bounds(pivot: NodeIndex, other: Bounds,
+        tree: &mut impl TreeWriter<K, V>
+    ) {
+        if tree.meta().root.is_none() {
+            *tree.meta_mut() = other;
+            Self::insert_end::<0>(pivot, tree);
+        } else if other.root.is_none() {
+            Self::insert_end::<1>(pivot, tree);
+        } else if tree.meta().black_height >= other.black_height {
+            Self::join_unchecked::<0>(tree, pivot, other);
+        } else {
+            let this = replace(tree.meta_mut(), other);
+            Self::join_unchecked::<1>(tree, pivot, this);
+        }
+    }
+    /// Split the tree at an in-order position in `O(log(N))`, the tree keeps the first `n` nodes.
+    /// Returns the bounds of the remaining nodes, they stay linked in order and are detached from the tree.
+    ///
+    /// # Safety
+    /// The returned nodes are no longer part of the tree, they have to be joined back or freed.
+    unsafe fn split_off(n: usize,
+        tree: &mut impl TreeWriter<K, V>
+    ) -> Bounds {
+        if n == 0 {
+            return replace(tree.meta_mut(), Bounds::default());
+        }
+        if n >= tree.meta().len {
+            return Bounds::default();
+        }
+        // SAFETY: the tree is not empty
+        let left_len = Self::size(tree[tree.meta().root.unwrap()].children[0], tree);
+        let (Some(root), right) = Self::split_root(tree)
+            else { unreachable!("tree is not empty") };
+        match n.cmp(&left_len) {
+            Ordering::Equal => {
+                let left = replace(tree.meta_mut(), right);
+                Self::insert_end::<0>(root, tree);
+                replace(tree.meta_mut(), left)
+            },
+            Ordering::Less => {
+                let center = Self::split_off(n, tree);
+                let left = replace(tree.meta_mut(), center);
+                Self::join_bounds(root, right, tree);
+                replace(tree.meta_mut(), left)
+            },
+            Ordering::Greater => {
+                let left = replace(tree.meta_mut(), right);
+                let rest = Self::split_off(n - left_len - 1, tree);
+                let center = replace(tree.meta_mut(), left);
+                Self::join_bounds(root, center, tree);
+                rest
+            }
+        }
+    }
+    /// Append the other tree in `O(log(N))`, all keys of the other tree are ordered after the keys of the tree.
+    ///
+    /// # Safety
+    /// All nodes of the other tree have to be owned by tree and will be moved into the tree.
+    unsafe fn append(other: Bounds,
+        tree: &mut impl TreeWriter<K, V>
+    ) {
+        let Some(min) = other.range[0]
+            else { return };
+        let this = replace(tree.meta_mut(), other);
+        let pivot = Self::remove_at(min, tree);
+        let other = replace(tree.meta_mut(), this);
+        Self::join_bounds(pivot, other, tree);
+    }
+    /// Free all nodes of a detached tree by following the order links, this takes `O(N)`.
+    ///
+    /// # Safety
+    /// The nodes must not be part of any tree.
+    unsafe fn free_detached(bounds: Bounds,
+        tree: &mut PortAllocGuard<Node<K, V>, Bounds>
+    ) {
+        let mut ptr = bounds.range[0];
+        while let Some(index) = ptr {
+            ptr = tree[index].order[1];
+            tree.remove(index);
+        }
+    }
+}
+impl<K, V: Value, C: Comparator<K>> Tree<K, V, C> {
     /// # Note
     /// There is no fast-pass for empty trees, that should be checked by the caller.
     ///
@@ -648,35 +1025,36 @@ impl<K: Ord, V: Value> Tree<K, V> {
                 return Ok(self);
             }
         }
+        let cmp = self.cmp.clone();
         let mut this = self.write();
         let mut that = other.write();
         let center = &this.0[pivot].key;
         // SAFETY: both trees are not empty here
-        if this.max().unwrap() < center
-            && center < that.min().unwrap()
+        if cmp.compare(this.max().unwrap(), center).is_lt()
+            && cmp.compare(center, that.min().unwrap()).is_lt()
         {
             if this.0.meta().black_height >= that.0.meta().black_height {
                 drop(that);
-                Self::join_unchecked::<0>(&mut this.0, pivot, other.port);
+                Tree::join_unchecked::<0>(&mut this.0, pivot, other.port.free());
                 drop(this);
                 Ok(self)
             } else {
                 drop(this);
-                Self::join_unchecked::<1>(&mut that.0, pivot, self.port);
+                Tree::join_unchecked::<1>(&mut that.0, pivot, self.port.free());
                 drop(that);
                 Ok(other)
             }
-        } else if that.max().unwrap() < center
-            && center < this.min().unwrap()
+        } else if cmp.compare(that.max().unwrap(), center).is_lt()
+            && cmp.compare(center, this.min().unwrap()).is_lt()
         {
             if this.0.meta().black_height >= that.0.meta().black_height {
                 drop(that);
-                Self::join_unchecked::<1>(&mut this.0, pivot, other.port);
+                Tree::join_unchecked::<1>(&mut this.0, pivot, other.port.free());
                 drop(this);
                 Ok(self)
             } else {
                 drop(this);
-                Self::join_unchecked::<0>(&mut that.0, pivot, self.port);
+                Tree::join_unchecked::<0>(&mut that.0, pivot, self.port.free());
                 drop(that);
                 Ok(other)
             }
@@ -685,4 +1063,36 @@ impl<K: Ord, V: Value> Tree<K, V> {
             Err(((self, other), Error::Overlapping))
         }
     }
+}
+impl<K, V: Value, C> Tree<K, V, C> {
+    /// Join two trees together with a pivot between them, without comparing any keys.
+    ///
+    /// # Safety
+    /// The pivot will be moved into this tree and should not be referenced by any other tree after this.
+    ///
+    /// `self->max->key <= pivot->key <= other->min->key`
+    #[inline]
+    pub(crate) unsafe fn join_ordered(mut self, pivot: NodeIndex, mut other: Self) -> Self {
+        if self.read().is_empty() {
+            other.write().insert_node_at::<0>(pivot);
+            return other;
+        }
+        if other.read().is_empty() {
+            self.write().insert_node_at::<1>(pivot);
+            return self;
+        }
+        let mut this = self.write();
+        let mut that = other.write();
+        if this.0.meta().black_height >= that.0.meta().black_height {
+            drop(that);
+            Tree::join_unchecked::<0>(&mut this.0, pivot, other.port.free());
+            drop(this);
+            self
+        } else {
+            drop(this);
+            Tree::join_unchecked::<1>(&mut that.0, pivot, self.port.free());
+            drop(that);
+            other
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,273 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    marker::PhantomData,
+    ops::{Bound, Deref, DerefMut, RangeBounds}
+};
+
+use crate::{
+    arena::Meta,
+    tree::{
+        Tree, TreeReader, Node, Value, ValueMut, Comparator, OrdComparator, Error,
+        TreeReadGuard, TreeWriteGuard, TreeAllocGuard,
+        VacantEntry, Iter, IntoIter
+    }
+};
+
+/// [Tree] that allows duplicate keys, equal keys are kept in insertion order.
+///
+/// This is constructed using [MultiWeakForest](crate::MultiWeakForest).
+#[derive(Debug)]
+pub struct MultiTree<K, V: Value, C = OrdComparator>(pub(crate) Tree<K, V, C>);
+impl<K, V: Value, C> MultiTree<K, V, C> {
+    /// Aquire read-only access.
+    #[inline(always)]
+    pub fn read(&self) -> MultiTreeReadGuard<K, V, C> {
+        MultiTreeReadGuard(self.0.read())
+    }
+    /// Aquire read-write access.
+    #[inline(always)]
+    pub fn write(&mut self) -> MultiTreeWriteGuard<K, V, C> {
+        MultiTreeWriteGuard(self.0.write())
+    }
+    /// Aquire insert/remove access.
+    #[inline(always)]
+    pub fn alloc(&mut self) -> MultiTreeAllocGuard<K, V, C> {
+        MultiTreeAllocGuard(self.0.alloc())
+    }
+    /// Returns the comparator defining the order of the keys.
+    #[inline(always)]
+    pub fn comparator(&self) -> &C {
+        self.0.comparator()
+    }
+    /// Split tree at a given key.
+    /// Returns the keys below, the run of keys equal to the given key and the keys above.
+    #[inline]
+    pub fn split<Q>(self, key: &Q) -> (Self, Self, Self)
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
+        let range = {
+            let read = self.0.read();
+            let range = (Bound::Included(key), Bound::Included(key));
+            // SAFETY: root is a node in tree
+            unsafe { Tree::rank_range(read.0.meta().root, &range, read.1.comparator(), &read.0) }
+        };
+        let (left, rest) = self.0.split_at(range.start);
+        let (center, right) = rest.split_at(range.len());
+        (Self(left), Self(center), Self(right))
+    }
+    /// Join two trees together.
+    /// Runs of equal keys are kept in order, with the nodes of this tree before the nodes of the other tree.
+    ///
+    /// Both trees have to belong to the same forest, otherwise they are returned with the error.
+    #[inline]
+    pub fn union(self, other: Self) -> Result<Self, ((Self, Self), Error)>
+        where C: Comparator<K>
+    {
+        if !self.0.port.shares_arena(&other.0.port) {
+            return Err(((self, other), Error::ForeignTree));
+        }
+        #[inline]
+        fn helper<K, V: Value, C: Comparator<K>>(this: Tree<K, V, C>, other: Tree<K, V, C>) -> Tree<K, V, C> {
+            if this.read().is_empty() {
+                return other;
+            }
+            if other.read().is_empty() {
+                return this;
+            }
+            let (other_left, Some(other_root), other_right) = other.split_at_root()
+                else { unreachable!("other is not empty") };
+            let rank = {
+                let read = this.read();
+                // SAFETY: root is a node in tree, other_root is a node in the same arena
+                unsafe { Tree::rank_after(read.0.meta().root, &read.0[other_root].key, read.1.comparator(), &read.0) }
+            };
+            let (left, right) = this.split_at(rank);
+            let left = helper(left, other_left);
+            let right = helper(right, other_right);
+            // SAFETY: equal keys of this tree were split to the left of other_root
+            unsafe { left.join_ordered(other_root, right) }
+        }
+        Ok(Self(helper(self.0, other.0)))
+    }
+}
+impl<K, V: Value, C> IntoIterator for MultiTree<K, V, C> {
+    type IntoIter = IntoIter<K, V>;
+    type Item = <Self::IntoIter as Iterator>::Item;
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+macro_rules! impl_MultiReader {
+    ( $type:ident ) => {
+        impl<'a, K, V: Value, C> $type <'a, K, V, C> {
+            /// Returns an in-order iterator over all nodes with the given key, in insertion order.
+            #[inline]
+            pub fn get_all<Q>(&self, key: &Q) -> Iter<K, V, impl TreeReader<K, V> + 'a>
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let range = (Bound::Included(key), Bound::Included(key));
+                let [front, back] = Tree::range_bounds(&range, self.0.1.comparator(), &self.0.0);
+                Iter { tree: &self.0.0, front, back, _phantom: PhantomData }
+            }
+            /// Returns the number of nodes with the given key.
+            #[inline]
+            pub fn count<Q>(&self, key: &Q) -> usize
+                where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+            {
+                let range = (Bound::Included(key), Bound::Included(key));
+                // SAFETY: root is a node in tree
+                unsafe { Tree::rank_range(self.0.0.meta().root, &range, self.0.1.comparator(), &self.0.0) }
+                    .len()
+            }
+        }
+    };
+}
+
+/// Read-only access to a [MultiTree].
+///
+/// All methods of [TreeReadGuard] are available through [Deref],
+/// lookups return an arbitrary node when there are multiple nodes with the same key.
+#[derive(Debug)]
+pub struct MultiTreeReadGuard<'a, K, V: Value, C = OrdComparator>(pub(crate) TreeReadGuard<'a, K, V, C>);
+impl<'a, K, V: Value, C> Deref for MultiTreeReadGuard<'a, K, V, C> {
+    type Target = TreeReadGuard<'a, K, V, C>;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl_MultiReader!(MultiTreeReadGuard);
+
+/// Read-write access to a [MultiTree].
+///
+/// All methods of [TreeWriteGuard] are available through [Deref],
+/// lookups return an arbitrary node when there are multiple nodes with the same key.
+#[derive(Debug)]
+pub struct MultiTreeWriteGuard<'a, K, V: Value, C = OrdComparator>(pub(crate) TreeWriteGuard<'a, K, V, C>);
+impl<'a, K, V: Value, C> Deref for MultiTreeWriteGuard<'a, K, V, C> {
+    type Target = TreeWriteGuard<'a, K, V, C>;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl<'a, K, V: Value, C> DerefMut for MultiTreeWriteGuard<'a, K, V, C> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+impl_MultiReader!(MultiTreeWriteGuard);
+
+/// Insert/remove access to a [MultiTree].
+///
+/// Only operations that keep runs of equal keys in insertion order are available.
+#[derive(Debug)]
+pub struct MultiTreeAllocGuard<'a, K, V: Value, C = OrdComparator>(pub(crate) TreeAllocGuard<'a, K, V, C>);
+impl<'a, K, V: Value, C> MultiTreeAllocGuard<'a, K, V, C> {
+    #[inline]
+    pub fn downgrade(self) -> MultiTreeWriteGuard<'a, K, V, C> {
+        MultiTreeWriteGuard(self.0.downgrade())
+    }
+    /// Inserts the value after all values with an equal key, returns a mutable reference to the new value.
+    #[inline]
+    pub fn insert(&mut self, key: K, value: V::Local) -> ValueMut<'_, K, V>
+        where C: Comparator<K>
+    {
+        let cmp = self.0.1.comparator();
+        // NOTE: equal keys are treated as smaller, so the search ends after all of them
+        let compare = |node: &Node<K, V>| match cmp.compare(&node.key, &key) {
+            Ordering::Greater => Ordering::Greater,
+            _ => Ordering::Less
+        };
+        // SAFETY: root is a node in tree
+        let parent = unsafe { Tree::search_by(self.0.0.meta().root, compare, &self.0.0) };
+        VacantEntry { tree: &mut self.0.0, key, parent }.insert(value)
+    }
+    /// Removes the first inserted value with the given key.
+    #[inline]
+    pub fn remove_one<Q>(&mut self, key: &Q) -> Option<V::Into>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
+        let cmp = self.0.1.comparator();
+        let ptr = Tree::closest_bound::<1, _, _>(Bound::Included(key), cmp, &self.0.0)?;
+        if cmp.compare(self.0.0[ptr].key.borrow(), key).is_ne() {
+            return None;
+        }
+        // SAFETY: node is part of tree
+        let ptr = unsafe { Tree::remove_at(ptr, &mut self.0.0) };
+        // SAFETY: node was unlinked, so it exists
+        Some(self.0.0.remove(ptr).unwrap().value.into())
+    }
+    /// Removes all values with the given key, returns the number of removed values.
+    #[inline]
+    pub fn remove_all<Q>(&mut self, key: &Q) -> usize
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
+        let drain = self.0.drain_range((Bound::Included(key), Bound::Included(key)));
+        let len = drain.len();
+        drop(drain);
+        len
+    }
+    /// Removes the lowest key, the first inserted value is removed for a run of equal keys.
+    #[inline(always)]
+    pub fn pop_first(&mut self) -> Option<(K, V::Into)> {
+        self.0.pop_first()
+    }
+    /// Removes the biggest key, the last inserted value is removed for a run of equal keys.
+    #[inline(always)]
+    pub fn pop_last(&mut self) -> Option<(K, V::Into)> {
+        self.0.pop_last()
+    }
+    /// Removes all nodes for which the function returns `false`.
+    #[inline(always)]
+    pub fn retain<F>(&mut self, keep: F)
+        where F: FnMut(&K, V::Mut<'_>) -> bool
+    {
+        self.0.retain(keep)
+    }
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+    /// Returns the number of nodes.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Returns the position of the first node with the given key,
+    /// or the position it would be inserted at as an error.
+    #[inline(always)]
+    pub fn rank<Q>(&self, key: &Q) -> Result<usize, usize>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>
+    {
+        self.0.rank(key)
+    }
+    /// Returns the cumulant of the whole tree.
+    #[inline(always)]
+    pub fn cumulant(&self) -> Option<&V::Cumulant> {
+        self.0.cumulant()
+    }
+    /// Returns an in-order iterator, equal keys are visited in insertion order.
+    #[inline]
+    pub fn iter(&self) -> Iter<K, V, impl TreeReader<K, V> + 'a> {
+        let [front, back] = self.0.0.meta().range;
+        Iter { tree: &self.0.0, front, back, _phantom: PhantomData }
+    }
+    /// Returns an in-order iterator confined to the given range of keys.
+    #[inline]
+    pub fn range<Q, R>(&self, range: R) -> Iter<K, V, impl TreeReader<K, V> + 'a>
+        where K: Borrow<Q>, Q: ?Sized, C: Comparator<Q>, R: RangeBounds<Q>
+    {
+        let [front, back] = Tree::range_bounds(&range, self.0.1.comparator(), &self.0.0);
+        Iter { tree: &self.0.0, front, back, _phantom: PhantomData }
+    }
+}
+impl_MultiReader!(MultiTreeAllocGuard);
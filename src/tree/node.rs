@@ -1,8 +1,11 @@
-use std::ops::{Deref, DerefMut, Not};
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Not}
+};
 
 use crate::{
     arena::Index,
-    tree::Tree
+    tree::{Tree, TreeWriter}
 };
 
 pub(crate) type NodeIndex = Index;
@@ -28,26 +31,105 @@ impl const Not for Color {
 }
 /// Smart pointer to a [Value].
 /// This will ensure that any changes to the value will cause the updated cumulants to be propagated throughout the tree.
+///
+/// The cumulants are propagated through the write guard that created this object,
+/// instead of locking the tree again, which would deadlock while the guard is still alive.
+/// The guard is stored type-erased, so the same type works for every guard.
 #[derive(Debug)]
-pub struct ValueMut<'a, K: Ord, V: Value>(pub(crate) V::Mut<'a>, pub(crate) Index, pub(crate) &'a Tree<K, V>);
-impl<'a, K: Ord, V: Value> const Deref for ValueMut<'a, K, V> {
+pub struct ValueMut<'a, K, V: Value>(
+    pub(crate) V::Mut<'a>, pub(crate) Index,
+    *mut (), unsafe fn(*mut (), Index),
+    PhantomData<&'a Tree<K, V>>
+);
+// SAFETY: a shared reference only gives access to the value, the tree pointer is only used in drop.
+unsafe impl<'a, K, V: Value> Sync for ValueMut<'a, K, V>
+    where V::Mut<'a>: Sync {}
+impl<'a, K, V: Value> ValueMut<'a, K, V> {
+    /// # Safety
+    /// The value has to belong to the node pointer and the node pointer has to be owned by tree.
+    ///
+    /// The tree has to be locked for writing and exclusively borrowed for as long as the returned object lives,
+    /// it will be used to propagate the cumulants on drop.
+    /// No other reference into the tree may be handed out next to the returned object.
+    #[inline(always)]
+    pub(crate) unsafe fn new<W: TreeWriter<K, V>>(value: V::Mut<'a>, ptr: Index, tree: *mut W) -> Self {
+        Self(value, ptr, tree as *mut (), propagate::<K, V, W>, PhantomData)
+    }
+}
+unsafe fn propagate<K, V: Value, W: TreeWriter<K, V>>(tree: *mut (), ptr: Index) {
+    // SAFETY: the type was erased in the constructor
+    Tree::propagate_cumulant(ptr, (tree as *mut W).as_mut().unwrap())
+}
+impl<'a, K, V: Value> const Deref for ValueMut<'a, K, V> {
     type Target = V::Mut<'a>;
     #[inline(always)]
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
-impl<'a, K: Ord, V: Value> const DerefMut for ValueMut<'a, K, V> {
+impl<'a, K, V: Value> const DerefMut for ValueMut<'a, K, V> {
     #[inline(always)]
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
-impl<'a, K: Ord, V: Value> const Drop for ValueMut<'a, K, V> {
+impl<'a, K, V: Value> const Drop for ValueMut<'a, K, V> {
     #[inline(always)]
     fn drop(&mut self) {
-        let mut write = self.2.port.write();
-        unsafe { Tree::propagate_cumulant(self.1, &mut write) }
+        if V::has_cumulant() {
+            // SAFETY: guarantied by constructor
+            unsafe { (self.3)(self.2, self.1) }
+        }
+    }
+}
+/// Mutable access to `N` distinct values and read-only access to `R` other values of the same tree.
+///
+/// Unlike [ValueMut] the references are only handed out for as long as this object is borrowed,
+/// the cumulants of all mutable values are propagated together on drop.
+#[derive(Debug)]
+pub struct ValuesMut<'a, K, V: Value, const N: usize, const R: usize = 0>(
+    [Option<(Index, *mut Node<K, V>)>; N], [Option<*const Node<K, V>>; R],
+    *mut (), unsafe fn(*mut (), Index),
+    PhantomData<&'a mut Tree<K, V>>
+);
+impl<'a, K, V: Value, const N: usize, const R: usize> ValuesMut<'a, K, V, N, R> {
+    /// # Safety
+    /// The nodes have to be distinct from each other and from the read-only nodes,
+    /// all of them have to be owned by tree.
+    ///
+    /// The tree has to be locked for writing and exclusively borrowed for as long as the returned object lives,
+    /// it will be used to propagate the cumulants on drop.
+    #[inline(always)]
+    pub(crate) unsafe fn new<W: TreeWriter<K, V>>(
+        nodes: [Option<(Index, *mut Node<K, V>)>; N], others: [Option<*const Node<K, V>>; R], tree: *mut W
+    ) -> Self {
+        Self(nodes, others, tree as *mut (), propagate::<K, V, W>, PhantomData)
+    }
+    /// Returns the mutable references.
+    #[inline]
+    pub fn get(&mut self) -> [Option<V::Mut<'_>>; N] {
+        self.get_with().0
+    }
+    /// Returns the mutable references together with the read-only references.
+    #[inline]
+    pub fn get_with(&mut self) -> ([Option<V::Mut<'_>>; N], [Option<V::Ref<'_>>; R]) {
+        (
+            // SAFETY: the nodes are distinct and the tree is borrowed for as long as self
+            self.0.map( |node| node.map( |(_, node)| unsafe { (*node).value.get_mut_unchecked() } ) ),
+            // SAFETY: the nodes are not aliased by the mutable ones
+            self.1.map( |node| node.map( |node| unsafe { (*node).value.get() } ) )
+        )
+    }
+}
+impl<'a, K, V: Value, const N: usize, const R: usize> Drop for ValuesMut<'a, K, V, N, R> {
+    #[inline]
+    fn drop(&mut self) {
+        if V::has_cumulant() {
+            for (ptr, _) in self.0.iter().flatten() {
+                // SAFETY: guarantied by constructor, no references into the tree are left at this point
+                unsafe { (self.3)(self.2, *ptr) }
+            }
+        }
     }
 }
 /// All values meant to be used with [Tree] need to implement this trait.
@@ -82,6 +164,14 @@ pub trait Value {
     /// [Value::update_cumulant] will only be called when this returns `true`.
     fn has_cumulant() -> bool;
 }
+/// Values whose cumulants can be combined across consecutive ranges of nodes,
+/// this is required by [Tree::split_by_cumulant](crate::tree::Tree::split_by_cumulant).
+pub trait CombineCumulant: Value {
+    /// Cumulant of the current node as if it had no children.
+    fn local_cumulant(&self) -> Self::Cumulant;
+    /// Combines the cumulants of two consecutive ranges of nodes, `left` preceding `right`.
+    fn combine_cumulants(left: &Self::Cumulant, right: &Self::Cumulant) -> Self::Cumulant;
+}
 /// This type implements [Value] without cumulants.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct NoCumulant<T>(T);
@@ -117,20 +207,74 @@ impl<T> const Value for NoCumulant<T> {
     #[inline(always)]
     fn has_cumulant() -> bool { false }
 }
+impl<T> CombineCumulant for NoCumulant<T> {
+    #[inline(always)]
+    fn local_cumulant(&self) -> Self::Cumulant { }
+    #[inline(always)]
+    fn combine_cumulants(_left: &Self::Cumulant, _right: &Self::Cumulant) -> Self::Cumulant { }
+}
 /// Generates a new type and implements the [Value] trait.
 ///
+/// The optional `combine` block has to compute the cumulant of two consecutive ranges of nodes,
+/// when given the [CombineCumulant] trait is implemented as well.
+/// The cumulant of a single node is computed by the update block with both children set to the default,
+/// so the default has to be an identity element of `combine`.
+///
 /// # Examples
 /// ```rust
 /// with_cumulant!(
 ///     WithSum(value: &i32, children: [&i32] = 0) {
 ///         value + children[0] + children[1]
 ///     }
+///     combine(left, right) {
+///         left + right
+///     }
 /// )
 /// ```
 // TODO: support passing by clone/copy
 // TODO: support for mutating cumulant, instead of return
 #[macro_export]
 macro_rules! with_cumulant {
+    {
+        $visibility:vis $typename:ident $( <
+            $( $param:tt $( : $( $constraint:path ),+ )? ),*
+        > )? (
+            $valuename:ident : & $valuetype:ty ,
+            $childrenname:ident : [ & $cumulanttype:ty ] = $cumulantdefault:expr
+        )
+        $updatebody:block
+        combine ( $leftname:ident , $rightname:ident )
+        $combinebody:block
+    } => {
+        $crate::with_cumulant! {
+            $visibility $typename $( <
+                $( $param $( : $( $constraint ),+ )? ),*
+            > )? (
+                $valuename : & $valuetype ,
+                $childrenname : [ & $cumulanttype ] = $cumulantdefault
+            )
+            $updatebody
+        }
+        impl $( <
+            $( $param $( : $( $constraint ),+ )? ),*
+        > )?
+        CombineCumulant for $typename $( <
+            $( $param ),*
+        > )? {
+            #[inline(always)]
+            fn local_cumulant(&self) -> Self::Cumulant {
+                let $valuename = &self.0;
+                #[allow(non_snake_case)]
+                let __default__ = $cumulantdefault;
+                let $childrenname = [&__default__, &__default__];
+                $updatebody
+            }
+            #[inline(always)]
+            fn combine_cumulants($leftname: &Self::Cumulant, $rightname: &Self::Cumulant) -> Self::Cumulant {
+                $combinebody
+            }
+        }
+    };
     {
         $visibility:vis $typename:ident $( <
             $( $param:tt $( : $( $constraint:path ),+ )? ),*
@@ -195,22 +339,25 @@ macro_rules! with_cumulant {
 pub use with_cumulant;
 
 #[derive(Debug)]
-pub(crate) struct Node<K: Ord, V: Value> {
+pub(crate) struct Node<K, V: Value> {
     pub key: K,
     pub value: V,
     pub color: Color,
     pub parent: NodeRef,
     pub children: [NodeRef; 2],
-    pub order: [NodeRef; 2]
+    pub order: [NodeRef; 2],
+    /// Number of nodes in the sub-tree rooted at this node.
+    pub size: usize
 }
-impl<K: Ord, V: Value> Node<K, V> {
+impl<K, V: Value> Node<K, V> {
     #[inline]
     pub const fn new(key: K, value: V, color: Color) -> Self {
         Self {
             key, value, color,
             parent: None,
             children: [None, None],
-            order: [None, None]
+            order: [None, None],
+            size: 1
         }
     }
     #[inline(always)]
@@ -232,6 +379,7 @@ impl<K: Ord, V: Value> Node<K, V> {
         self.parent = None;
         self.order = [None, None];
         self.children = [None, None];
+        self.size = 1;
         self.color = color;
     }
 }
\ No newline at end of file